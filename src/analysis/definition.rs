@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+use crate::parsing::{Expr, ExprKind, Span};
+
+/// Finds the span of the binder of the identifier at the byte offset `pos`
+/// Binders are introduced by lets, lambda parameters, match patterns and constructors of type declarations
+pub fn definition(expr: &Expr, pos: usize) -> Option<Span> {
+    resolve(expr, pos, &mut vec![])
+}
+
+fn resolve<'e>(expr: &'e Expr, pos: usize, scope: &mut Vec<(&'e str, Span)>) -> Option<Span> {
+    if !expr.span.contains(pos) {
+        // Bindings still have to be introduced for the expressions that come after them
+        declare(expr, scope);
+        return None
+    }
+    let depth = scope.len();
+    let found = match &expr.kind {
+        ExprKind::Id { name } => scope.iter().rev().find(|(n, _)| n == name).map(|(_, span)| *span),
+        ExprKind::Let { binder, bound } => {
+            // Lambda bindings are visible inside their own body
+            if bound.is_lambda() { scope.push((&binder.name, binder.span)) }
+            let found = resolve(bound, pos, scope);
+            scope.truncate(depth);
+            found
+        }
+        ExprKind::LetIn { binder, bound, body } => {
+            if bound.is_lambda() { scope.push((&binder.name, binder.span)) }
+            let found = resolve(bound, pos, scope);
+            scope.truncate(depth);
+            scope.push((&binder.name, binder.span));
+            found.or_else(|| resolve(body, pos, scope))
+        }
+        ExprKind::Lambda { params, body, .. } => {
+            scope.extend(params.iter().map(|binder| (binder.name.as_str(), binder.span)));
+            resolve(body, pos, scope)
+        }
+        ExprKind::Block { exprs, .. } => {
+            // Lambda bindings are recursive unless they shadow an earlier binding of the block
+            let mut names = HashSet::new();
+            let recursive = exprs.iter().map(|e| match &e.kind {
+                ExprKind::Let { binder, bound } => names.insert(binder.name.as_str()) && bound.is_lambda(),
+                _ => false,
+            }).collect::<Vec<_>>();
+            let mut found = None;
+            for (i, e) in exprs.iter().enumerate() {
+                // A run of recursive bindings is mutually recursive so every binder of the run is visible from the start
+                if recursive[i] && (i == 0 || !recursive[i - 1]) {
+                    exprs[i..].iter().zip(&recursive[i..]).take_while(|(_, &r)| r).for_each(|(e, _)| declare(e, scope));
+                }
+                found = match &e.kind {
+                    ExprKind::Let { bound, .. } if bound.is_lambda() && !recursive[i] && e.span.contains(pos) => resolve(bound, pos, scope),
+                    _ => resolve(e, pos, scope),
+                };
+                if found.is_some() { break }
+            }
+            found
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            let mut found = resolve(scrutinee, pos, scope);
+            for arm in arms {
+                if found.is_some() { break }
+                scope.extend(arm.pat.bindings());
+                found = resolve(&arm.body, pos, scope);
+                scope.truncate(depth);
+            }
+            found
+        }
+        _ => expr.children().into_iter().find_map(|e| resolve(e, pos, scope)),
+    };
+    scope.truncate(depth);
+    found
+}
+
+/// Introduces the bindings an expression makes into the enclosing scope
+fn declare<'e>(expr: &'e Expr, scope: &mut Vec<(&'e str, Span)>) {
+    match &expr.kind {
+        ExprKind::Let { binder, .. } => scope.push((&binder.name, binder.span)),
+        ExprKind::TypeDef { variants, .. } => scope.extend(variants.iter().map(|variant| (variant.name.as_str(), variant.span))),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn definition_src(src: &str, pos: usize) -> Option<usize> {
+        let (_, expr) = crate::generate_ast(src).unwrap();
+        definition(&expr, pos).map(|span| span.lo)
+    }
+
+    #[test] fn let_binding() { assert_eq!(definition_src("{ let x = 5; x }", 13), Some(6)) }
+    #[test] fn lambda_param() { assert_eq!(definition_src("fn x => fn y => x", 16), Some(3)) }
+    #[test] fn shadowing() { assert_eq!(definition_src("{ let x = 5; let x = x; x }", 24), Some(17)) }
+    #[test] fn shadowed_bound_refers_to_outer() { assert_eq!(definition_src("{ let x = 5; let x = x; x }", 21), Some(6)) }
+    #[test] fn recursive() { assert_eq!(definition_src("{ let f = fn n => f(n); f }", 18), Some(6)) }
+    #[test] fn mutually_recursive() { assert_eq!(definition_src("{ let f = fn n => g(n); let g = fn n => f(n); 1 }", 18), Some(28)) }
+    #[test] fn shadowing_lambda() { assert_eq!(definition_src("{ let f = fn x => x; let f = fn y => f(y); f(1) }", 37), Some(6)) }
+    #[test] fn let_in_body() { assert_eq!(definition_src("let x = 5 in let x = x in x", 26), Some(17)) }
+    #[test] fn pattern_binding() { assert_eq!(definition_src("match (1, 2) { (x, y) => y }", 25), Some(19)) }
+    #[test] fn constructor() { assert_eq!(definition_src("{ type T = A | B(Int); B(1) }", 23), Some(15)) }
+    #[test] fn unbound_in_other_scope() { assert_eq!(definition_src("{ (fn x => x); fn y => y }", 23), Some(18)) }
+}
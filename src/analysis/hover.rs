@@ -0,0 +1,44 @@
+use crate::parsing::{Expr, ExprKind, Span};
+use crate::typechecking::{Ty, Normalizer};
+
+/// Describes the innermost expression or binder at the byte offset `pos` of a typechecked tree
+/// Identifiers are shown along with their name, e.g. `f: (i64) -> i64`
+pub fn hover(expr: &Expr, pos: usize) -> Option<(Span, String)> {
+    if !expr.span.contains(pos) { return None }
+    let binders = match &expr.kind {
+        ExprKind::Let { binder, .. } | ExprKind::LetIn { binder, .. } => vec![binder],
+        ExprKind::Lambda { params, .. } => params.iter().collect(),
+        _ => vec![],
+    };
+    if let Some(binder) = binders.into_iter().find(|binder| binder.span.contains(pos)) {
+        return Some((binder.span, format!("{}: {}", binder.name, normalized(&binder.ty))))
+    }
+    if let Some(hover) = expr.children().into_iter().find_map(|e| hover(e, pos)) {
+        return Some(hover)
+    }
+    match &expr.kind {
+        ExprKind::Id { name } => Some((expr.span, format!("{}: {}", name, normalized(&expr.ty)))),
+        _ => Some((expr.span, normalized(&expr.ty).to_string())),
+    }
+}
+
+fn normalized(ty: &Ty) -> Ty {
+    let mut ty = ty.clone();
+    Normalizer::new().normalize(&mut ty);
+    ty
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hover_src(src: &str, pos: usize) -> String {
+        let (_, expr) = crate::generate_ast(src).unwrap();
+        hover(&expr, pos).unwrap().1
+    }
+
+    #[test] fn hover_id() { assert_eq!(hover_src("{ let f = fn x => x + 1; f(2) }", 25), "f: (i64) -> i64") }
+    #[test] fn hover_binder() { assert_eq!(hover_src("{ let f = fn x => x + 1; f(2) }", 14), "x: i64") }
+    #[test] fn hover_expression() { assert_eq!(hover_src("(1, true)", 0), "(i64, bool)") }
+    #[test] fn hover_polymorphic() { assert_eq!(hover_src("{ let id = fn x => x; id }", 7), "id: (a) -> a") }
+}
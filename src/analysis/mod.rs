@@ -0,0 +1,5 @@
+mod hover;
+mod definition;
+
+pub use hover::hover;
+pub use definition::definition;
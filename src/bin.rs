@@ -1,29 +1,116 @@
-#![feature(box_syntax)]
 use rustyline::{Editor};
 use rustyline::error::{ReadlineError};
 use std::env;
-use parserlib::{generate_ast_with_err_handling, generate_ast};
-use parserlib::Formatter;
+use parserlib::{Error, Formatter, ErrorFormat, Evaluator, Printer, SourceMap, Session, TypecheckMode};
+
+fn usage() -> ! {
+    println!("[usage] [--error-format=human|json] [--typecheck=constraints|bidirectional] [--colour] <file>");
+    println!("        fmt [--check] [--width=N] [--colour] <file>");
+    std::process::exit(1)
+}
+
+fn report(formatter: &Formatter, errors: &[Error], format: ErrorFormat) {
+    formatter.write(&mut std::io::stdout(), errors, format).expect("Failed to write to stdout")
+}
+
+/// Stdout only carries the errors in the JSON error format so everything else is written to stderr
+fn output(format: ErrorFormat, line: impl std::fmt::Display) {
+    match format {
+        ErrorFormat::Human => println!("{}", line),
+        ErrorFormat::Json => eprintln!("{}", line),
+    }
+}
+
+/// Prints the canonically formatted file, or with `--check` exits with failure if the file is not formatted
+fn fmt(args: &[String], flags: &[String]) -> ! {
+    let mut check = false;
+    let mut width = 80;
+    let mut colour = false;
+    for flag in flags {
+        match flag.as_str() {
+            "--check" => check = true,
+            "--colour" => colour = true,
+            flag if flag.starts_with("--width=") => width = flag["--width=".len()..].parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let path = match args {
+        [path] => path,
+        _ => usage(),
+    };
+
+    let contents = std::fs::read_to_string(path).expect("Failed to read file");
+    // Formatting from the concrete syntax tree keeps the comments
+    let formatted = match parserlib::parse_cst(&contents).and_then(|cst| Printer::new(width).print_cst(&cst)) {
+        Ok(formatted) => format!("{}\n", formatted),
+        Err(errors) => {
+            let mut map = SourceMap::new();
+            map.add(path.as_str(), contents.as_str());
+            report(&Formatter::with_source_map(&map).with_colour(colour), &errors, ErrorFormat::Human);
+            std::process::exit(1)
+        }
+    };
+    if check {
+        if formatted != contents {
+            println!("{} is not formatted", path);
+            std::process::exit(1)
+        }
+    } else {
+        print!("{}", formatted);
+    }
+    std::process::exit(0)
+}
 
 fn main() {
     let mut rl = Editor::<()>::new();
 
-    if env::args().len() > 2 {
-        println!("[usage] <file>");
-        std::process::exit(1)
+    let (flags, args): (Vec<String>, Vec<String>) = env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    if args.first().map(String::as_str) == Some("fmt") { fmt(&args[1..], &flags) }
+
+    let mut format = ErrorFormat::Human;
+    let mut mode = TypecheckMode::Constraints;
+    // Colour is opt in so that diagnostics piped elsewhere are not full of escape codes
+    let mut colour = false;
+    for flag in flags {
+        match flag.as_str() {
+            "--error-format=human" => format = ErrorFormat::Human,
+            "--error-format=json" => format = ErrorFormat::Json,
+            "--typecheck=constraints" => mode = TypecheckMode::Constraints,
+            "--typecheck=bidirectional" => mode = TypecheckMode::Bidirectional,
+            "--colour" => colour = true,
+            _ => usage(),
+        }
     }
 
-    if env::args().len() == 2 {
-        let contents = std::fs::read_to_string(&env::args().collect::<Vec<String>>()[1]).expect("Failed to read file");
-        let (ty, ast) = generate_ast_with_err_handling(&contents);
-        println!("{:?}", ast);
-        println!("{}", ast);
-        println!("{}", ty);
+    if args.len() > 1 { usage() }
+
+    if let Some(path) = args.first() {
+        let contents = std::fs::read_to_string(path).expect("Failed to read file");
+        let mut session = Session::new().with_mode(mode);
+        let file = session.add_file(path.as_str(), contents);
+        let compilation = session.compile(file);
+        let formatter = Formatter::with_source_map(session.source_map()).with_colour(colour);
+        let (ty, ast) = match compilation.into_result() {
+            Ok(x) => x,
+            Err(errors) => {
+                report(&formatter, &errors, format);
+                std::process::exit(1)
+            }
+        };
+        output(format, format_args!("{:?}", ast));
+        output(format, &ast);
+        match Evaluator::new().eval(&ast) {
+            Ok(value) => output(format, format_args!("{}: {}", value, ty)),
+            Err(error) => {
+                report(&formatter, &[error], format);
+                std::process::exit(1)
+            }
+        }
         std::process::exit(0);
     }
 
     if rl.load_history("history.txt").is_err() {
-        println!("No previous history.");
+        output(format, "No previous history.");
     }
 
     loop {
@@ -36,28 +123,32 @@ fn main() {
                 line
             },
             Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C");
+                output(format, "CTRL-C");
                 break
             },
             Err(ReadlineError::Eof) => {
-                println!("CTRL-D");
+                output(format, "CTRL-D");
                 break
             },
             Err(err) => {
-                println!("Error: {:?}", err);
+                output(format, format_args!("Error: {:?}", err));
                 break
             }
         };
 
-        match generate_ast(&line) {
+        let mut session = Session::new().with_mode(mode);
+        let file = session.add_file("", line.as_str());
+        match session.compile(file).into_result() {
             Ok((ty, ast)) => {
-                println!("{:?}", ast);
-                println!("{}", ast);
-                println!("{}", ty);
+                output(format, format_args!("{:?}", ast));
+                output(format, &ast);
+                match Evaluator::new().eval(&ast) {
+                    Ok(value) => output(format, format_args!("{}: {}", value, ty)),
+                    Err(error) => report(&Formatter::new(&line).with_colour(colour), &[error], format),
+                }
             }
             Err(errors) => {
-                let formatter = Formatter::new(&line);
-                formatter.write(errors);
+                report(&Formatter::new(&line).with_colour(colour), &errors, format);
             }
         };
 
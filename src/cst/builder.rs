@@ -0,0 +1,108 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+use crate::lexing::Token;
+use crate::parsing::{Expr, Parser};
+use super::{SyntaxNode, SyntaxToken, SyntaxElement, NodeKind, TriviaKind};
+use super::trivia::lex_trivia;
+
+type Tokens = Peekable<IntoIter<SyntaxToken>>;
+
+/// Builds the concrete syntax tree of a parsed expression
+/// Tokens are assigned to the innermost expression whose span contains them and the source between tokens becomes trivia
+pub(crate) fn build(src: &str, tokens: &[Token], expr: &Expr) -> SyntaxNode {
+    let mut tokens = with_trivia(src, tokens).into_iter().peekable();
+    let mut children = vec![SyntaxElement::Node(node(expr, &mut tokens))];
+    children.extend(tokens.map(SyntaxElement::Token));
+    SyntaxNode::new(NodeKind::Root, children)
+}
+
+fn node(expr: &Expr, tokens: &mut Tokens) -> SyntaxNode {
+    let mut children = vec![];
+    for child in expr.children() {
+        take_until(tokens, child.span.lo, &mut children);
+        children.push(SyntaxElement::Node(node(child, tokens)));
+    }
+    take_until(tokens, expr.span.hi, &mut children);
+    SyntaxNode::new(NodeKind::of(&expr.kind), children)
+}
+
+fn take_until(tokens: &mut Tokens, pos: usize, children: &mut Vec<SyntaxElement>) {
+    while let Some(token) = tokens.peek() {
+        if token.span.lo >= pos { break }
+        children.extend(tokens.next().map(SyntaxElement::Token));
+    }
+}
+
+fn with_trivia(src: &str, tokens: &[Token]) -> Vec<SyntaxToken> {
+    let mut syntax_tokens: Vec<SyntaxToken> = Vec::with_capacity(tokens.len());
+    let mut pos = 0;
+    for token in tokens {
+        let mut trivia = lex_trivia(&src[pos..token.index], pos);
+        if let Some(prev) = syntax_tokens.last_mut() {
+            let line_end = trivia.iter().position(|t| t.kind == TriviaKind::Newline).unwrap_or(trivia.len());
+            let rest = trivia.split_off(line_end);
+            prev.trailing = std::mem::replace(&mut trivia, rest);
+        }
+        syntax_tokens.push(SyntaxToken {
+            kind: token.kind,
+            text: token.lexeme.to_owned(),
+            span: Parser::token_span(*token),
+            leading: trivia,
+            trailing: vec![],
+        });
+        pos = token.index + token.lexeme.len();
+    }
+    syntax_tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cst::TriviaKind;
+    use crate::util::Counter;
+    use crate::lexing::{Lexer, TokenKind};
+
+    fn cst(src: &str) -> SyntaxNode {
+        let tokens = Lexer::new(src).lex().unwrap();
+        let mut gen = Counter::new();
+        let expr = Parser::new(&tokens, &mut gen).parse().unwrap();
+        build(src, &tokens, &expr)
+    }
+
+    #[test]
+    fn reproduces_source() {
+        let src = "// leading\n{\n    let x = 5; // five\n    /* the answer */ x   +  1\n}\n";
+        assert_eq!(cst(src).to_string(), src);
+    }
+
+    #[test]
+    fn attaches_trivia() {
+        let tokens = cst("{ let x = 5; // five\n x }").tokens().into_iter().cloned().collect::<Vec<_>>();
+        let semi = tokens.iter().find(|t| t.kind == TokenKind::SemiColon).unwrap();
+        assert_eq!(semi.trailing.iter().map(|t| t.kind).collect::<Vec<_>>(), vec![TriviaKind::Whitespace, TriviaKind::LineComment]);
+        let x = tokens.iter().filter(|t| t.text == "x").nth(1).unwrap();
+        assert_eq!(x.leading.iter().map(|t| t.kind).collect::<Vec<_>>(), vec![TriviaKind::Newline, TriviaKind::Whitespace]);
+    }
+
+    #[test]
+    fn nests_nodes_by_expression() {
+        let root = cst("f(1 + 2, x)");
+        let app = root.child_nodes().next().unwrap();
+        assert_eq!(app.kind, NodeKind::App);
+        assert_eq!(app.child_nodes().map(|n| n.kind).collect::<Vec<_>>(), vec![NodeKind::Id, NodeKind::Binary, NodeKind::Id]);
+        let binary = app.child_nodes().nth(1).unwrap();
+        assert_eq!(binary.to_string(), "1 + 2");
+        // Separators belong to the enclosing node
+        assert_eq!(app.children.iter().filter(|c| match c { SyntaxElement::Token(t) => t.kind == TokenKind::Comma, _ => false }).count(), 1);
+    }
+
+    #[test]
+    fn converts_to_expr() {
+        let root = cst("{ let f = fn x => x * 2; /* call */ f(3) }");
+        let block = root.child_nodes().next().unwrap();
+        assert_eq!(format!("{:?}", block.to_expr().unwrap().kind), format!("{:?}", root.to_expr().unwrap().kind));
+        let call = block.child_nodes().nth(1).unwrap().to_expr().unwrap();
+        assert_eq!(call.to_string(), "f(3)");
+        assert_eq!(call.span.lo, 36);
+    }
+}
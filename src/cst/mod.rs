@@ -0,0 +1,7 @@
+mod node;
+mod trivia;
+mod builder;
+
+pub use node::{SyntaxNode, SyntaxToken, SyntaxElement, NodeKind};
+pub use trivia::{Trivia, TriviaKind};
+pub(crate) use builder::build;
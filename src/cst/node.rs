@@ -0,0 +1,156 @@
+use std::fmt::{self, Display, Formatter};
+use crate::lexing::{Token, TokenKind};
+use crate::parsing::{ExprKind, Expr, Parser, Span};
+use crate::error::Error;
+use crate::util::Counter;
+use super::Trivia;
+
+/// The kind of expression a node was built from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NodeKind {
+    /// The whole source file including the end of file token
+    Root,
+    Unary,
+    Integral,
+    Floating,
+    Bool,
+    Id,
+    Str,
+    Binary,
+    Grouping,
+    Let,
+    LetIn,
+    Block,
+    Lambda,
+    App,
+    Tuple,
+    Record,
+    Update,
+    Access,
+    If,
+    TypeDef,
+    Match,
+    Cast,
+    Class,
+    Instance,
+}
+
+impl NodeKind {
+    pub fn of(kind: &ExprKind) -> Self {
+        match kind {
+            ExprKind::Unary { .. }    => Self::Unary,
+            ExprKind::Integral { .. } => Self::Integral,
+            ExprKind::Floating { .. } => Self::Floating,
+            ExprKind::Bool { .. }     => Self::Bool,
+            ExprKind::Id { .. }       => Self::Id,
+            ExprKind::Str { .. }      => Self::Str,
+            ExprKind::Binary { .. }   => Self::Binary,
+            ExprKind::Grouping { .. } => Self::Grouping,
+            ExprKind::Let { .. }      => Self::Let,
+            ExprKind::LetIn { .. }    => Self::LetIn,
+            ExprKind::Block { .. }    => Self::Block,
+            ExprKind::Lambda { .. }   => Self::Lambda,
+            ExprKind::App { .. }      => Self::App,
+            ExprKind::Tuple { .. }    => Self::Tuple,
+            ExprKind::Record { .. }   => Self::Record,
+            ExprKind::Update { .. }   => Self::Update,
+            ExprKind::Access { .. }   => Self::Access,
+            ExprKind::If { .. }       => Self::If,
+            ExprKind::TypeDef { .. }  => Self::TypeDef,
+            ExprKind::Match { .. }    => Self::Match,
+            ExprKind::Cast { .. }     => Self::Cast,
+            ExprKind::Class { .. }    => Self::Class,
+            ExprKind::Instance { .. } => Self::Instance,
+        }
+    }
+}
+
+/// A token along with the trivia surrounding it
+/// Trailing trivia runs up to the end of the token's line, everything else leads the next token
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.leading.iter().try_for_each(|trivia| write!(f, "{}", trivia))?;
+        write!(f, "{}", self.text)?;
+        self.trailing.iter().try_for_each(|trivia| write!(f, "{}", trivia))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl Display for SyntaxElement {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Node(node)   => write!(f, "{}", node),
+            Self::Token(token) => write!(f, "{}", token),
+        }
+    }
+}
+
+/// A lossless concrete syntax tree
+/// Each node holds the tokens of its expression that are not part of a subexpression, interleaved with the subexpressions' nodes
+/// Displaying a node reproduces its source text exactly
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn new(kind: NodeKind, children: Vec<SyntaxElement>) -> Self {
+        Self { kind, children }
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token of the subtree in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        self.children.iter().flat_map(|child| match child {
+            SyntaxElement::Node(node) => node.tokens(),
+            SyntaxElement::Token(token) => vec![token],
+        }).collect()
+    }
+
+    /// The span from the first to the last token, excluding trivia
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        Some(tokens.first()?.span.merge(tokens.last()?.span))
+    }
+
+    /// Converts the subtree to an expression by parsing its tokens
+    /// Spans in the expression refer to the original source
+    pub fn to_expr(&self) -> Result<Expr, Vec<Error>> {
+        let mut tokens = self.tokens().into_iter()
+            .filter(|token| token.kind != TokenKind::EOF)
+            // Lines and columns are not used by the parser
+            .map(|token| Token { kind: token.kind, lexeme: &token.text, index: token.span.lo, line: 0, col: 0 })
+            .collect::<Vec<_>>();
+        let hi = tokens.last().map_or(0, |token| token.index + token.lexeme.len());
+        tokens.push(Token { kind: TokenKind::EOF, lexeme: "", index: hi, line: 0, col: 0 });
+        let mut gen = Counter::new();
+        Parser::new(&tokens, &mut gen).parse()
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.children.iter().try_for_each(|child| write!(f, "{}", child))
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt::{self, Display, Formatter};
+use crate::parsing::Span;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+}
+
+/// Source text between tokens that has no meaning to the parser
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Display for Trivia {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{}", self.text) }
+}
+
+/// Splits the text between two tokens, which starts at `lo`, into trivia
+pub(crate) fn lex_trivia(text: &str, mut lo: usize) -> Vec<Trivia> {
+    let mut trivia = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (kind, len) = if rest.starts_with('\n') {
+            (TriviaKind::Newline, 1)
+        } else if rest.starts_with("//") {
+            (TriviaKind::LineComment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (TriviaKind::BlockComment, rest.find("*/").map(|i| i + 2).unwrap_or(rest.len()))
+        } else {
+            // Anything else the lexer skipped is kept as whitespace so no text is lost
+            let len = rest.find(|c: char| c == '\n' || c == '/' || !c.is_whitespace()).unwrap_or(rest.len());
+            (TriviaKind::Whitespace, if len > 0 { len } else { rest.chars().next().map_or(1, char::len_utf8) })
+        };
+        let (text, tail) = rest.split_at(len);
+        trivia.push(Trivia { kind, text: text.to_owned(), span: Span::new(lo, lo + len) });
+        lo += len;
+        rest = tail;
+    }
+    trivia
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_trivia() {
+        let trivia = lex_trivia("  // note\n\t/* a\nb */ ", 4);
+        let kinds = trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            TriviaKind::Whitespace, TriviaKind::LineComment, TriviaKind::Newline,
+            TriviaKind::Whitespace, TriviaKind::BlockComment, TriviaKind::Whitespace,
+        ]);
+        assert_eq!(trivia[4].text, "/* a\nb */");
+        assert_eq!(trivia[4].span, Span::new(15, 24));
+    }
+}
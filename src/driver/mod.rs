@@ -0,0 +1,3 @@
+mod session;
+
+pub use session::{Session, Compilation, Stage};
@@ -0,0 +1,171 @@
+use crate::error::Error;
+use crate::lexing::Lexer;
+use crate::parsing::{Parser, Expr};
+use crate::source::{SourceMap, FileId};
+use crate::typechecking::{Typechecker, TypecheckMode, Ty};
+use crate::util::Counter;
+
+/// The parts of the pipeline, in the order they run
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+    Lex,
+    Parse,
+    Typecheck,
+}
+
+/// The outcome of compiling a single file
+/// Each stage only runs if the previous one succeeded
+#[derive(Debug)]
+pub struct Compilation {
+    pub file: FileId,
+    /// The tree if parsing succeeded, annotated with solved types if typechecking succeeded too
+    pub expr: Option<Expr>,
+    pub ty: Option<Ty>,
+    pub lex_errors: Vec<Error>,
+    pub parse_errors: Vec<Error>,
+    pub type_errors: Vec<Error>,
+}
+
+impl Compilation {
+    fn new(file: FileId) -> Self {
+        Self { file, expr: None, ty: None, lex_errors: vec![], parse_errors: vec![], type_errors: vec![] }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.lex_errors.is_empty() && self.parse_errors.is_empty() && self.type_errors.is_empty()
+    }
+
+    /// Every error in the order the stages ran
+    pub fn errors(&self) -> impl Iterator<Item = &Error> {
+        self.lex_errors.iter().chain(&self.parse_errors).chain(&self.type_errors)
+    }
+
+    pub fn into_result(self) -> Result<(Ty, Expr), Vec<Error>> {
+        match (self.ty, self.expr) {
+            (Some(ty), Some(expr)) => Ok((ty, expr)),
+            _ => Err(self.lex_errors.into_iter().chain(self.parse_errors).chain(self.type_errors).collect()),
+        }
+    }
+}
+
+type Tracer = Box<dyn FnMut(Stage, &str)>;
+
+/// Compiles files held in its source map without printing or exiting
+/// Debug output such as the tokens, tree and constraints is only produced when a tracer is given
+pub struct Session {
+    map: SourceMap,
+    tracer: Option<Tracer>,
+    mode: TypecheckMode,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self { map: SourceMap::new(), tracer: None, mode: TypecheckMode::Constraints }
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mode(mut self, mode: TypecheckMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_tracer(mut self, tracer: impl FnMut(Stage, &str) + 'static) -> Self {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
+
+    pub fn add_file(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        self.map.add(name, src)
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.map
+    }
+
+    pub fn compile(&mut self, id: FileId) -> Compilation {
+        let Self { map, tracer, mode } = self;
+        let file = map.file(id);
+        let mut tracer = tracer.as_mut().map(|tracer| &mut **tracer as &mut dyn FnMut(Stage, &str));
+        let mut trace = |stage, msg: &dyn Fn() -> String| if let Some(tracer) = &mut tracer { tracer(stage, &msg()) };
+        let mut compilation = Compilation::new(id);
+
+        let tokens = match Lexer::with_offset(&file.src, file.start).lex() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                compilation.lex_errors = errors;
+                return compilation
+            }
+        };
+        trace(Stage::Lex, &|| format!("tokens: {:?}", tokens));
+
+        let mut gen = Counter::new();
+        let mut expr = match Parser::new(&tokens, &mut gen).parse() {
+            Ok(expr) => expr,
+            Err(errors) => {
+                compilation.parse_errors = errors;
+                return compilation
+            }
+        };
+        trace(Stage::Parse, &|| format!("ast: {:?}", expr));
+
+        let mut typechecker = Typechecker::new(&mut gen).with_mode(*mode);
+        if let Some(tracer) = &mut tracer { typechecker = typechecker.with_tracer(&mut **tracer) }
+        match typechecker.typecheck(&mut expr) {
+            Ok(ty) => {
+                let substitution = typechecker.into_substitution();
+                Typechecker::apply_to_tree(&mut expr, &substitution);
+                compilation.ty = Some(ty);
+            }
+            Err(errors) => compilation.type_errors = errors,
+        }
+        compilation.expr = Some(expr);
+        compilation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn separates_errors_by_stage() {
+        let mut session = Session::new();
+        let lex = session.add_file("lex", "1 # 2");
+        let parse = session.add_file("parse", "1 +");
+        let types = session.add_file("types", "1 + false");
+        assert_eq!(session.compile(lex).lex_errors[0].code, Some(ErrorCode::UnknownCharacter));
+        assert_eq!(session.compile(parse).parse_errors.len(), 1);
+        let compilation = session.compile(types);
+        assert!(compilation.expr.is_some() && compilation.ty.is_none());
+        assert_eq!(compilation.type_errors[0].code, Some(ErrorCode::TypeMismatch));
+    }
+
+    #[test]
+    fn spans_refer_to_their_file() {
+        let mut session = Session::new();
+        session.add_file("a", "let x = 1;");
+        let b = session.add_file("b", "true + 1");
+        let compilation = session.compile(b);
+        let span = compilation.errors().next().unwrap().span;
+        let file = session.source_map().lookup(span.lo).unwrap();
+        assert_eq!(file.name, "b");
+    }
+
+    #[test]
+    fn traces_each_stage() {
+        let stages = Rc::new(RefCell::new(vec![]));
+        let traced = Rc::clone(&stages);
+        let mut session = Session::new().with_tracer(move |stage, _| traced.borrow_mut().push(stage));
+        let file = session.add_file("main", "fn x => x");
+        assert!(session.compile(file).is_ok());
+        assert_eq!(*stages.borrow(), vec![Stage::Lex, Stage::Parse, Stage::Typecheck]);
+    }
+}
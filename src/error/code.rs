@@ -0,0 +1,52 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Stable identifiers for each kind of error
+/// Codes must never be reused or renumbered once released
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorCode {
+    // Syntax errors
+    UnexpectedToken      = 1,
+    UnexpectedEof        = 2,
+    UnknownCharacter     = 3,
+    UnterminatedString   = 4,
+    UnterminatedComment  = 5,
+    IntegerOverflow      = 6,
+    FloatOverflow        = 7,
+    // Type errors
+    UnboundVariable      = 100,
+    TypeMismatch         = 101,
+    InfiniteType         = 102,
+    UnknownType          = 103,
+    TypeArity            = 104,
+    UnboundTypeVariable  = 105,
+    UnknownConstructor   = 106,
+    ConstructorArity     = 107,
+    DuplicateConstructor = 108,
+    DuplicateBinding     = 109,
+    NonExhaustive        = 110,
+    UnreachablePattern   = 111,
+    UnknownOperator      = 112,
+    ArgumentCount        = 113,
+    InvalidCast          = 114,
+    NoInstance           = 115,
+    UnknownClass         = 116,
+    DuplicateClass       = 117,
+    DuplicateInstance    = 118,
+    InvalidInstance      = 119,
+    TupleArity           = 120,
+    MissingField         = 121,
+    UnexpectedField      = 122,
+    DuplicateField       = 123,
+    DuplicateType        = 124,
+    // Runtime errors
+    DivisionByZero       = 200,
+    Overflow             = 201,
+    InvalidOperand       = 202,
+    MatchFailure         = 203,
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "E{:04}", *self as u32)
+    }
+}
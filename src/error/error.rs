@@ -1,13 +1,69 @@
+use std::fmt::{self, Display, Formatter};
 use crate::parsing::Span;
+use super::ErrorCode;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Error {
     pub span: Span,
     pub msg: String,
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    /// Secondary spans that explain the error
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A secondary span with a message of its own, e.g. "expected because of this annotation"
+#[derive(Clone, PartialEq, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub msg: String,
 }
 
 impl Error {
     pub fn new(span: Span, msg: String) -> Self {
-        Error { span, msg }
+        Error { span, msg, severity: Severity::Error, code: None, labels: vec![], help: None }
+    }
+
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        Error { code: Some(code), ..self }
+    }
+
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Error { severity, ..self }
+    }
+
+    pub fn with_label(mut self, span: Span, msg: String) -> Self {
+        self.labels.push(Label { span, msg });
+        self
+    }
+
+    pub fn with_help(self, help: String) -> Self {
+        Error { help: Some(help), ..self }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Severity::Error   => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Displays the header of the diagnostic, e.g. `error[E0101]: Failed to unify type i64 with bool`
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}[{}]: {}", self.severity, code, self.msg),
+            None => write!(f, "{}: {}", self.severity, self.msg),
+        }
     }
 }
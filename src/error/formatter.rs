@@ -1,54 +1,242 @@
-use crate::error::Error;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use crate::error::{Error, Severity};
 use crate::parsing::Span;
+use crate::source::{SourceMap, SourceFile, Loc};
+use crate::json::Json;
+
+/// How diagnostics are written by the binary
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorFormat {
+    /// Rustc style output
+    Human,
+    /// One JSON object per line
+    Json,
+}
 
 pub struct Formatter<'a> {
-    src: &'a str,
-    lines: Vec<&'a str>,
+    map: Cow<'a, SourceMap>,
+    colour: bool,
 }
 
+/// ANSI colour codes
+const RED: &str = "31";
+const YELLOW: &str = "33";
+const BLUE: &str = "34";
+
+/// A span to underline along with the character to underline it with and an optional message
+type Annotation<'e> = (Span, char, Option<&'e str>);
+
+/// Spans covering more lines than this only show their first two and last lines
+const MAX_SPAN_LINES: usize = 4;
+
 impl<'a> Formatter<'a> {
-    pub fn new(src: &'a str) -> Self {
-        Self { src, lines: src.lines().collect() }
+    /// Formats errors in a single unnamed source
+    pub fn new(src: &str) -> Self {
+        let mut map = SourceMap::new();
+        map.add("", src);
+        Self { map: Cow::Owned(map), colour: false }
     }
 
-    pub fn write(&self, errors: Vec<Error>) {
-        for error in errors {  self.write_err(error) }
+    pub fn with_source_map(map: &'a SourceMap) -> Self {
+        Self { map: Cow::Borrowed(map), colour: false }
     }
 
-    fn slice(&self, span: &Span) -> &str {
-        &self.src[span.lo..span.hi]
+    /// Colours human readable output with ANSI escape codes
+    pub fn with_colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
     }
 
-    /// Returns a triple of the line and the two surrounding it
-    /// Remember: the line count starts from 1 while index from 0
-    fn surrounding(&self, line: usize) -> (&str, &str, &str) {
-        let prev = if line > 1 { self.lines[line - 2] } else { "" };
-        let curr = self.lines[line - 1];
-        let next = if line < self.lines.len() { self.lines[line] } else { "" };
-        (prev, curr, next)
+    /// Writes each error followed by a blank line, or as one JSON object per line
+    pub fn write(&self, out: &mut dyn Write, errors: &[Error], format: ErrorFormat) -> io::Result<()> {
+        for error in errors {
+            match format {
+                ErrorFormat::Human => writeln!(out, "{}", self.render(error))?,
+                ErrorFormat::Json  => writeln!(out, "{}", self.to_json(error))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the error as a single line JSON object
+    /// Lines and columns are one indexed and columns count characters rather than bytes
+    pub fn to_json(&self, error: &Error) -> Json {
+        let labels = error.labels.iter()
+            .map(|label| Json::object(vec![("message", Json::from(label.msg.as_str())), ("span", self.span_json(label.span))]))
+            .collect::<Vec<_>>();
+        Json::object(vec![
+            ("message", Json::from(error.msg.as_str())),
+            ("code", Json::from(error.code.map(|code| code.to_string()))),
+            ("severity", Json::from(error.severity.to_string())),
+            ("span", self.span_json(error.span)),
+            ("labels", Json::from(labels)),
+            ("help", Json::from(error.help.clone())),
+        ])
     }
 
-    pub fn write_err(&self, error: Error) {
-        let Error { span, msg } = &error;
-        self.print_relevant_src(&error);
-        // let slice = self.slice(span);
-        red!("{}", msg)
+    fn span_json(&self, span: Span) -> Json {
+        let (start, end) = self.file(span).span_locs(span);
+        let position = |loc: Loc| Json::object(vec![("line", Json::from(loc.line + 1)), ("col", Json::from(loc.col + 1))]);
+        Json::object(vec![
+            ("lo", Json::from(span.lo)),
+            ("hi", Json::from(span.hi)),
+            ("start", position(start)),
+            ("end", position(end)),
+        ])
     }
 
-    fn print_relevant_src(&self, error: &Error) {
-        let line = error.span.line;
-        let (prev, curr, next) = self.surrounding(line);
-        green_ln!("Relevant source code:");
-        if !prev.is_empty() {
-            blue!("{}: ", line - 1);
-            println!("{}", prev);
+    /// Renders the error in the style of rustc
+    /// The primary span is underlined with carets and each label with dashes on every line they cover
+    pub fn render(&self, error: &Error) -> String {
+        let mut out = String::new();
+        let colour = match error.severity {
+            Severity::Error   => RED,
+            Severity::Warning => YELLOW,
+        };
+        out.push_str(&self.paint(colour, &error.to_string()));
+        out.push('\n');
+
+        let file = self.file(error.span);
+        let mut annotations: Vec<Annotation> = vec![(error.span, '^', None)];
+        // Labels in other files cannot be shown alongside the primary span
+        annotations.extend(error.labels.iter()
+            .filter(|label| self.file(label.span).id == file.id)
+            .map(|label| (label.span, '-', Some(label.msg.as_str()))));
+        let lines = Self::lines_to_show(file, annotations.iter().map(|(span, ..)| *span));
+
+        let width = (lines[lines.len() - 1] + 1).to_string().len();
+        let gutter = " ".repeat(width);
+        let start = file.loc(error.span.lo);
+        let location = if file.name.is_empty() { format!("{}:{}", start.line + 1, start.col + 1) }
+            else { format!("{}:{}:{}", file.name, start.line + 1, start.col + 1) };
+        out.push_str(&format!("{}\n{}\n", self.paint(BLUE, &format!("{}--> {}", gutter, location)), self.paint(BLUE, &format!("{} |", gutter))));
+        for (i, &line) in lines.iter().enumerate() {
+            if i > 0 && line > lines[i - 1] + 1 { out.push_str(&format!("{}\n", self.paint(BLUE, "..."))) }
+            out.push_str(&format!("{}{}\n", self.paint(BLUE, &format!("{:>width$} | ", line + 1, width = width)), file.line(line)));
+            for (span, mark, msg) in &annotations {
+                let (first, last) = Self::line_range(file, *span);
+                if line < first || line > last { continue }
+                let underline = self.underline(*span, line, *mark);
+                let text = match msg {
+                    Some(msg) if line == last => format!("{} {}", underline, msg),
+                    _ => underline,
+                };
+                let colour = if *mark == '^' { RED } else { BLUE };
+                out.push_str(&format!("{}{}\n", self.paint(BLUE, &format!("{} | ", gutter)), self.paint(colour, &text)));
+            }
+        }
+        if let Some(help) = &error.help {
+            out.push_str(&format!("{}help: {}\n", self.paint(BLUE, &format!("{} = ", gutter)), help));
         }
-        blue!("{}: ", line);
-        println!("{}", curr);
-        if !next.is_empty() {
-            blue!("{}: ", line + 1);
-            println!("{}", next);
+        out
+    }
+
+    fn paint(&self, colour: &str, text: &str) -> String {
+        if self.colour { format!("\x1b[{}m{}\x1b[0m", colour, text) } else { text.to_owned() }
+    }
+
+    /// The file containing the span, falling back to the last file for spans beyond every file
+    fn file(&self, span: Span) -> &SourceFile {
+        self.map.lookup(span.lo).or_else(|| self.map.files().last()).expect("Formatting errors requires a source file")
+    }
+
+    /// The first and last lines covered by a span
+    /// A span ending at the start of a line does not cover that line
+    fn line_range(file: &SourceFile, span: Span) -> (usize, usize) {
+        let (start, end) = file.span_locs(span);
+        if end.line > start.line && end.col == 0 { (start.line, end.line - 1) } else { (start.line, end.line) }
+    }
+
+    /// The sorted lines covered by the spans, eliding the middle of long spans
+    fn lines_to_show(file: &SourceFile, spans: impl Iterator<Item = Span>) -> Vec<usize> {
+        let mut lines = vec![];
+        for span in spans {
+            let (first, last) = Self::line_range(file, span);
+            if last - first < MAX_SPAN_LINES { lines.extend(first..=last) }
+            else { lines.extend(vec![first, first + 1, last]) }
         }
+        lines.sort();
+        lines.dedup();
+        lines
+    }
+
+    /// Underlines the part of the span on the given line
+    /// Lines after the first are underlined from their first non whitespace character
+    fn underline(&self, span: Span, line: usize, mark: char) -> String {
+        let file = self.file(span);
+        let (start, end) = file.span_locs(span);
+        let text = file.line(line);
+        let lo = if line == start.line { start.col } else { text.chars().take_while(|c| c.is_whitespace()).count() };
+        let hi = if line == end.line { end.col } else { text.chars().count() };
+        let width = hi.saturating_sub(lo).max(1);
+        format!("{}{}", " ".repeat(lo), mark.to_string().repeat(width))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn underlines_span() {
+        let formatter = Formatter::new("let x = 5;\nx + false");
+        assert_eq!(formatter.underline(Span::new(15, 20), 1, '^'), "    ^^^^^")
+    }
+
+    #[test]
+    fn underlines_end_of_file() {
+        let formatter = Formatter::new("1 +");
+        assert_eq!(formatter.underline(Span::single(3), 0, '^'), "   ^")
+    }
+
+    #[test]
+    fn underlines_each_line_of_multiline_span() {
+        let formatter = Formatter::new("{ 1;\n 2 }");
+        assert_eq!(formatter.underline(Span::new(0, 9), 0, '-'), "----");
+        assert_eq!(formatter.underline(Span::new(0, 9), 1, '-'), " ---")
+    }
+
+    #[test]
+    fn elides_middle_of_long_spans() {
+        let mut map = SourceMap::new();
+        let id = map.add("main", "{\n1;\n2;\n3;\n4;\n5\n}\nx");
+        let file = map.file(id);
+        assert_eq!(Formatter::lines_to_show(file, vec![Span::new(0, 17), Span::single(18)].into_iter()), vec![0, 1, 6, 7]);
+        // Ending at the start of a line does not cover it
+        assert_eq!(Formatter::lines_to_show(file, vec![Span::new(2, 5)].into_iter()), vec![1]);
+    }
+
+    #[test]
+    fn renders_error() {
+        let formatter = Formatter::new("let x = 5;\nx + false");
+        let error = Error::new(Span::new(15, 20), "Failed to unify type bool with i64".to_owned())
+            .with_code(ErrorCode::TypeMismatch)
+            .with_label(Span::new(11, 12), "expected `i64` because of this".to_owned())
+            .with_help("try `x + 1`".to_owned());
+        assert_eq!(formatter.render(&error), concat!(
+            "error[E0101]: Failed to unify type bool with i64\n",
+            " --> 2:5\n",
+            "  |\n",
+            "2 | x + false\n",
+            "  |     ^^^^^\n",
+            "  | - expected `i64` because of this\n",
+            "  = help: try `x + 1`\n",
+        ))
+    }
 
+    #[test]
+    fn serializes_json() {
+        let formatter = Formatter::new("let x = 5;\nx + false");
+        let error = Error::new(Span::new(15, 20), "Failed to unify type \"bool\"".to_owned())
+            .with_code(ErrorCode::TypeMismatch)
+            .with_label(Span::new(0, 3), "here".to_owned());
+        assert_eq!(formatter.to_json(&error).to_string(), concat!(
+            r#"{"message":"Failed to unify type \"bool\"","code":"E0101","severity":"error","#,
+            r#""span":{"lo":15,"hi":20,"start":{"line":2,"col":5},"end":{"line":2,"col":10}},"#,
+            r#""labels":[{"message":"here","span":{"lo":0,"hi":3,"start":{"line":1,"col":1},"end":{"line":1,"col":4}}}],"#,
+            r#""help":null}"#,
+        ))
     }
 }
@@ -1,5 +1,8 @@
+#[allow(clippy::module_inception)]
 mod error;
+mod code;
 mod formatter;
 
-pub use error::Error;
-pub use formatter::Formatter;
+pub use error::{Error, Severity, Label};
+pub use code::ErrorCode;
+pub use formatter::{Formatter, ErrorFormat};
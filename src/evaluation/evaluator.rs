@@ -0,0 +1,366 @@
+use std::rc::Rc;
+use std::collections::HashSet;
+use crate::lexing::TokenKind;
+use crate::parsing::{Expr, ExprKind, Span, Pattern, PatternKind};
+use crate::typechecking::{Ty, TyKind};
+use crate::error::{Error, ErrorCode};
+use super::{Value, Frame};
+
+/// Tree walking interpreter over typechecked expressions
+/// As the input has been typechecked, most runtime errors here indicate a bug in the typechecker
+pub struct Evaluator<'a> {
+    env: Rc<Frame<'a>>,
+}
+
+impl<'a> Default for Evaluator<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new() -> Self {
+        Self { env: Frame::new() }
+    }
+
+    /// Evaluates the expression in the global scope
+    pub fn eval(&mut self, expr: &'a Expr) -> Result<Value<'a>, Error> {
+        let env = Rc::clone(&self.env);
+        self.eval_in(expr, &env)
+    }
+
+    fn eval_in(&mut self, expr: &'a Expr, env: &Rc<Frame<'a>>) -> Result<Value<'a>, Error> {
+        match &expr.kind {
+            ExprKind::Integral { value } => Ok(Value::Int(*value)),
+            ExprKind::Floating { value } => Ok(Value::Float(*value)),
+            ExprKind::Bool { b }         => Ok(Value::Bool(*b)),
+            ExprKind::Str { string }     => Ok(Value::Str(string.clone())),
+            ExprKind::Grouping { expr }  => self.eval_in(expr, env),
+            ExprKind::Id { name } => env.lookup(name)
+                .ok_or(Error::new(expr.span, format!("Unbound variable `{}`", name)).with_code(ErrorCode::UnboundVariable)),
+            ExprKind::Tuple { elems } => {
+                let values = elems.iter().map(|e| self.eval_in(e, env)).collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::Tuple(values))
+            }
+            ExprKind::Record { fields } => {
+                let mut values = fields.iter().map(|field| Ok((field.name.as_str(), self.eval_in(&field.value, env)?))).collect::<Result<Vec<_>, Error>>()?;
+                values.sort_by_key(|(name, _)| *name);
+                Ok(Value::Record(values))
+            }
+            ExprKind::Update { record, fields } => match self.eval_in(record, env)? {
+                Value::Record(mut values) => {
+                    for field in fields {
+                        let value = self.eval_in(&field.value, env)?;
+                        match values.iter_mut().find(|(name, _)| *name == field.name) {
+                            Some((_, old)) => *old = value,
+                            None => return Err(Error::new(field.span, format!("Record has no field `{}`", field.name)).with_code(ErrorCode::InvalidOperand)),
+                        }
+                    }
+                    Ok(Value::Record(values))
+                }
+                value => Err(Error::new(record.span, format!("Cannot update non-record value `{}`", value)).with_code(ErrorCode::InvalidOperand)),
+            },
+            ExprKind::Access { expr: record, field, field_span } => match self.eval_in(record, env)? {
+                Value::Record(values) => values.into_iter().find(|(name, _)| name == field).map(|(_, value)| value)
+                    .ok_or_else(|| Error::new(*field_span, format!("Record has no field `{}`", field)).with_code(ErrorCode::InvalidOperand)),
+                value => Err(Error::new(record.span, format!("Cannot access field `{}` of non-record value `{}`", field, value)).with_code(ErrorCode::InvalidOperand)),
+            },
+            ExprKind::Let { binder, bound } => {
+                let value = self.eval_in(bound, env)?;
+                env.define(&binder.name, value);
+                Ok(Value::unit()) // Let expressions always return unit
+            }
+            ExprKind::LetIn { binder, bound, body } => {
+                let let_env = Frame::child(env);
+                let value = self.eval_in(bound, &let_env)?;
+                let_env.define(&binder.name, value);
+                self.eval_in(body, &let_env)
+            }
+            ExprKind::Block { exprs, suppressed } => {
+                let mut block_env = Frame::child(env);
+                let mut value = Value::unit();
+                let mut grouped = false;
+                let mut names = HashSet::new();
+                for e in exprs {
+                    let shadows = match &e.kind {
+                        ExprKind::Let { binder, .. } => !names.insert(binder.name.as_str()),
+                        _ => false,
+                    };
+                    value = match &e.kind {
+                        // Consecutive lambda bindings share a frame so they may be mutually recursive
+                        // unless one shadows an earlier binding of the block, which it refers to instead
+                        ExprKind::Let { binder, bound } if bound.is_lambda() && !shadows => {
+                            if !grouped { block_env = Frame::child(&block_env) }
+                            let bound = self.eval_in(bound, &block_env)?;
+                            block_env.define(&binder.name, bound);
+                            Value::unit()
+                        }
+                        // Other bindings get their own frame so shadowing them later does not change what closures see
+                        ExprKind::Let { binder, bound } => {
+                            let bound = self.eval_in(bound, &block_env)?;
+                            block_env = Frame::child(&block_env);
+                            block_env.define(&binder.name, bound);
+                            Value::unit()
+                        }
+                        _ => self.eval_in(e, &block_env)?,
+                    };
+                    grouped = e.is_lambda_binding() && !shadows;
+                }
+                Ok(if *suppressed { Value::unit() } else { value })
+            }
+            ExprKind::TypeDef { variants, .. } => {
+                for variant in variants {
+                    let ctor = variant.name.as_str();
+                    let value = if variant.fields.is_empty() { Value::Adt { ctor, args: vec![] } }
+                        else { Value::Constructor { ctor, arity: variant.fields.len() } };
+                    env.define(ctor, value);
+                }
+                Ok(Value::unit())
+            }
+            // Classes have no methods so they only matter to the typechecker
+            ExprKind::Class { .. } | ExprKind::Instance { .. } => Ok(Value::unit()),
+            ExprKind::Lambda { params, body, .. } => {
+                let params = params.iter().map(|binder| binder.name.as_str()).collect();
+                Ok(Value::Closure { params, body, env: Rc::clone(env) })
+            }
+            ExprKind::App { f, args } => {
+                let f = self.eval_in(f, env)?;
+                let args = args.iter().map(|e| self.eval_in(e, env)).collect::<Result<Vec<_>, _>>()?;
+                self.apply(f, args, expr.span)
+            }
+            ExprKind::If { cond, then, otherwise } => match self.eval_in(cond, env)? {
+                Value::Bool(true) => {
+                    let value = self.eval_in(then, env)?;
+                    Ok(if otherwise.is_some() { value } else { Value::unit() })
+                }
+                Value::Bool(false) => match otherwise {
+                    Some(otherwise) => self.eval_in(otherwise, env),
+                    None => Ok(Value::unit()),
+                },
+                value => Err(Error::new(cond.span, format!("Expected boolean condition, found `{}`", value)).with_code(ErrorCode::InvalidOperand)),
+            },
+            ExprKind::Match { scrutinee, arms } => {
+                let value = self.eval_in(scrutinee, env)?;
+                for arm in arms {
+                    let arm_env = Frame::child(env);
+                    if Self::bind_pattern(&arm.pat, &value, &arm_env) { return self.eval_in(&arm.body, &arm_env) }
+                }
+                Err(Error::new(expr.span, format!("No pattern matched `{}`", value)).with_code(ErrorCode::MatchFailure))
+            }
+            ExprKind::Unary { op, expr: operand, .. } => {
+                let value = self.eval_in(operand, env)?;
+                Self::eval_unary(*op, value, expr.span)
+            }
+            ExprKind::Binary { op, left, right, .. } => {
+                let l = self.eval_in(left, env)?;
+                let r = self.eval_in(right, env)?;
+                Self::eval_binary(*op, l, r, expr.span)
+            }
+            ExprKind::Cast { expr: operand, ty } => {
+                let value = self.eval_in(operand, env)?;
+                Self::eval_cast(value, ty, expr.span)
+            }
+        }
+    }
+
+    fn apply(&mut self, f: Value<'a>, args: Vec<Value<'a>>, span: Span) -> Result<Value<'a>, Error> {
+        match f {
+            Value::Closure { params, body, env } => {
+                if params.len() != args.len() {
+                    return Err(Error::new(span, format!("Expected {} arguments, found {}", params.len(), args.len())).with_code(ErrorCode::ArgumentCount))
+                }
+                let call_env = Frame::child(&env);
+                params.into_iter().zip(args).for_each(|(param, arg)| call_env.define(param, arg));
+                self.eval_in(body, &call_env)
+            }
+            Value::Constructor { ctor, arity } => {
+                if arity != args.len() {
+                    return Err(Error::new(span, format!("Expected {} arguments, found {}", arity, args.len())).with_code(ErrorCode::ArgumentCount))
+                }
+                Ok(Value::Adt { ctor, args })
+            }
+            f => Err(Error::new(span, format!("Cannot apply non-function value `{}`", f)).with_code(ErrorCode::InvalidOperand)),
+        }
+    }
+
+    /// Returns whether the value matches the pattern, defining the bindings of the pattern in `env` if so
+    fn bind_pattern(pat: &'a Pattern, value: &Value<'a>, env: &Rc<Frame<'a>>) -> bool {
+        match (&pat.kind, value) {
+            (PatternKind::Wildcard, _)                        => true,
+            (PatternKind::Binding { name }, value)            => { env.define(name, value.clone()); true }
+            (PatternKind::Integral { value: i }, Value::Int(j)) => i == j,
+            (PatternKind::Bool { b }, Value::Bool(c))         => b == c,
+            (PatternKind::Str { string }, Value::Str(s))      => string == s,
+            (PatternKind::Tuple { elems }, Value::Tuple(xs))  =>
+                elems.len() == xs.len() && elems.iter().zip(xs).all(|(p, x)| Self::bind_pattern(p, x, env)),
+            (PatternKind::Constructor { name, args }, Value::Adt { ctor, args: xs }) =>
+                name == ctor && args.len() == xs.len() && args.iter().zip(xs).all(|(p, x)| Self::bind_pattern(p, x, env)),
+            _ => false,
+        }
+    }
+
+    fn eval_unary(op: TokenKind, value: Value<'a>, span: Span) -> Result<Value<'a>, Error> {
+        match (op, value) {
+            (TokenKind::Minus, Value::Int(i)) => i.checked_neg().map(Value::Int).ok_or(Self::overflow(span)),
+            (TokenKind::Plus, Value::Int(i))  => Ok(Value::Int(i)),
+            (TokenKind::Minus, Value::Float(x)) => Ok(Value::Float(-x)),
+            (TokenKind::Plus, Value::Float(x))  => Ok(Value::Float(x)),
+            (TokenKind::Tilde, Value::Int(i)) => Ok(Value::Int(!i)),
+            (TokenKind::Bang, Value::Bool(b)) => Ok(Value::Bool(!b)),
+            (op, value) => Err(Error::new(span, format!("Invalid operand `{}` for unary operator `{}`", value, op)).with_code(ErrorCode::InvalidOperand)),
+        }
+    }
+
+    fn eval_binary(op: TokenKind, l: Value<'a>, r: Value<'a>, span: Span) -> Result<Value<'a>, Error> {
+        match (op, l, r) {
+            (TokenKind::DEqual, l, r)    => Ok(Value::Bool(l == r)),
+            (TokenKind::BangEqual, l, r) => Ok(Value::Bool(l != r)),
+            (TokenKind::DPlus, Value::Str(x), Value::Str(y)) => Ok(Value::Str(x + &y)),
+            (op, Value::Int(x), Value::Int(y)) => match op {
+                TokenKind::Plus  => x.checked_add(y).map(Value::Int).ok_or(Self::overflow(span)),
+                TokenKind::Minus => x.checked_sub(y).map(Value::Int).ok_or(Self::overflow(span)),
+                TokenKind::Star  => x.checked_mul(y).map(Value::Int).ok_or(Self::overflow(span)),
+                TokenKind::Slash => if y == 0 { Err(Error::new(span, "Division by zero".to_owned()).with_code(ErrorCode::DivisionByZero)) }
+                    else { x.checked_div(y).map(Value::Int).ok_or(Self::overflow(span)) },
+                TokenKind::DStar => if y < 0 { Err(Error::new(span, format!("Negative exponent `{}`", y)).with_code(ErrorCode::InvalidOperand)) }
+                    else { x.checked_pow(y as u32).map(Value::Int).ok_or(Self::overflow(span)) },
+                TokenKind::LT    => Ok(Value::Bool(x < y)),
+                TokenKind::LTE   => Ok(Value::Bool(x <= y)),
+                TokenKind::GT    => Ok(Value::Bool(x > y)),
+                TokenKind::GTE   => Ok(Value::Bool(x >= y)),
+                op => Err(Error::new(span, format!("Invalid binary operator `{}`", op)).with_code(ErrorCode::UnknownOperator)),
+            },
+            // Floating point arithmetic follows IEEE 754 so division by zero gives an infinity rather than an error
+            (op, Value::Float(x), Value::Float(y)) => match op {
+                TokenKind::Plus  => Ok(Value::Float(x + y)),
+                TokenKind::Minus => Ok(Value::Float(x - y)),
+                TokenKind::Star  => Ok(Value::Float(x * y)),
+                TokenKind::Slash => Ok(Value::Float(x / y)),
+                TokenKind::DStar => Ok(Value::Float(x.powf(y))),
+                TokenKind::LT    => Ok(Value::Bool(x < y)),
+                TokenKind::LTE   => Ok(Value::Bool(x <= y)),
+                TokenKind::GT    => Ok(Value::Bool(x > y)),
+                TokenKind::GTE   => Ok(Value::Bool(x >= y)),
+                op => Err(Error::new(span, format!("Invalid binary operator `{}`", op)).with_code(ErrorCode::UnknownOperator)),
+            },
+            (op, l, r) => Err(Error::new(span, format!("Invalid operands `{}` and `{}` for binary operator `{}`", l, r, op)).with_code(ErrorCode::InvalidOperand)),
+        }
+    }
+
+    /// Floats are truncated towards zero when converted to integers
+    fn eval_cast(value: Value<'a>, ty: &Ty, span: Span) -> Result<Value<'a>, Error> {
+        match (value, &ty.kind) {
+            (Value::Int(i), TyKind::I64)   => Ok(Value::Int(i)),
+            (Value::Int(i), TyKind::F64)   => Ok(Value::Float(i as f64)),
+            (Value::Float(x), TyKind::F64) => Ok(Value::Float(x)),
+            // The upper bound is exclusive as `i64::MAX` rounds up to 2^63 as a float
+            (Value::Float(x), TyKind::I64) => if x >= i64::MIN as f64 && x < i64::MAX as f64 { Ok(Value::Int(x as i64)) }
+                else { Err(Self::overflow(span)) },
+            (value, _) => Err(Error::new(span, format!("Cannot cast `{}` to type {}", value, ty)).with_code(ErrorCode::InvalidOperand)),
+        }
+    }
+
+    fn overflow(span: Span) -> Error {
+        Error::new(span, "Integer overflow".to_owned()).with_code(ErrorCode::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! eval {
+        ($src:expr) => { {
+            let (_ty, ast) = crate::generate_ast($src).unwrap();
+            Evaluator::new().eval(&ast).unwrap().to_string()
+        } }
+    }
+
+    #[test] fn eval_int() { assert_eq!(eval!("5"), "5") }
+    #[test] fn eval_bool() { assert_eq!(eval!("false"), "false") }
+    #[test] fn eval_tuple() { assert_eq!(eval!("(1, false, (2, true))"), "(1, false, (2, true))") }
+    #[test] fn eval_let_in_block() { assert_eq!(eval!("{ let x = 5; let y = false; (y, x) }"), "(false, 5)") }
+    #[test] fn eval_shadowed_capture() { assert_eq!(eval!("{ let x = 1; let f = fn y => x; let x = false; f(0) }"), "1") }
+    #[test] fn eval_shadowing_lambda() { assert_eq!(eval!("{ let f = fn x => x + 1; let f = fn y => f(y) * 2; f(3) }"), "8") }
+    #[test] fn eval_suppressed_block() { assert_eq!(eval!("{ let x = 5; x; }"), "()") }
+    #[test] fn eval_application() { assert_eq!(eval!("(fn (x, y) => (y, x))(1, true)"), "(true, 1)") }
+    #[test] fn eval_string() { assert_eq!(eval!("\"hello\" ++ \" \" ++ \"world\""), "\"hello world\"") }
+    #[test] fn eval_arithmetic() { assert_eq!(eval!("1 + 2 * 3 ** 2 - 4 / 2"), "17") }
+    #[test] fn eval_float_arithmetic() { assert_eq!(eval!("(1.5 * 2.0 - 0.5, 1.0 / 4.0, 2.0 ** 0.5 > 1.41, -1e3)"), "(2.5, 0.25, true, -1000.0)") }
+    #[test] fn eval_cast() { assert_eq!(eval!("(7 as Float / 2.0, -3.9 as Int, (2.5 as Int) as Float)"), "(3.5, -3, 2.0)") }
+
+    #[test]
+    fn eval_cast_overflow() {
+        let (_ty, ast) = crate::generate_ast("1e19 as Int").unwrap();
+        assert!(Evaluator::new().eval(&ast).is_err())
+    }
+
+    #[test] fn eval_comparison() { assert_eq!(eval!("(1 < 2, 2 >= 3, (1, true) == (1, true), !true)"), "(true, false, true, false)") }
+
+    #[test]
+    fn eval_division_by_zero() {
+        let (_ty, ast) = crate::generate_ast("1 / 0").unwrap();
+        assert!(Evaluator::new().eval(&ast).is_err())
+    }
+
+    #[test] fn eval_let_in() { assert_eq!(eval!("let f = fn n => if n == 0 then 1 else n * f(n - 1) in let x = f(5) in (x, f(3))"), "(120, 6)") }
+    #[test] fn eval_polymorphic_let() { assert_eq!(eval!("{ let f = fn x => x; (f(4), f(false)) }"), "(4, false)") }
+
+    #[test]
+    fn eval_closure_captures_env() {
+        assert_eq!(eval!("{ let x = 5; let f = fn y => (x, y); { let x = false; f(x) } }"), "(5, false)")
+    }
+
+    #[test]
+    fn eval_mutually_recursive_bindings() {
+        assert_eq!(eval!("{ let f = fn x => g(x) * 2; let g = fn x => x + 1; f(1) }"), "4")
+    }
+
+    #[test]
+    fn eval_records() {
+        assert_eq!(eval!("{ let r = { y = true, x = 1 }; (r, r.x, { r | x = r.x + 1 }, r == { x = 1, y = true }) }"), "({ x = 1, y = true }, 1, { x = 2, y = true }, true)")
+    }
+
+    #[test] fn eval_if() { assert_eq!(eval!("(if 1 < 2 then 1 else 2, if false then 3)"), "(1, ())") }
+
+    #[test]
+    fn eval_recursive_factorial() {
+        assert_eq!(eval!("{ let fact = fn n => if n <= 1 then 1 else n * fact(n - 1); fact(10) }"), "3628800")
+    }
+
+    #[test]
+    fn eval_mutual_recursion() {
+        let src = "{
+            let even = fn n => if n == 0 then true else odd(n - 1);
+            let odd = fn n => if n == 0 then false else even(n - 1);
+            (even(10), odd(7), even(3))
+        }";
+        assert_eq!(eval!(src), "(true, true, false)")
+    }
+
+    #[test]
+    fn eval_constructors() {
+        assert_eq!(eval!("{ type Option<a> = None | Some(a); (Some(5), None, Some(1) == Some(1), Some(1) == None) }"), "(Some(5), None, true, false)")
+    }
+
+    #[test]
+    fn eval_match() {
+        let src = "{
+            type List<a> = Nil | Cons(a, List<a>);
+            let sum = fn l => match l { Nil => 0, Cons(x, xs) => x + sum(xs) };
+            let describe = fn p => match p { (0, _) => \"zero\", (_, true) => \"flagged\", _ => \"other\" };
+            (sum(Cons(1, Cons(2, Cons(3, Nil)))), describe((0, true)), describe((1, true)), describe((1, false)))
+        }";
+        assert_eq!(eval!(src), "(6, \"zero\", \"flagged\", \"other\")")
+    }
+
+    #[test]
+    fn eval_higher_order() {
+        let src = "{
+            let compose = fn (f, g) => fn x => f(g(x));
+            let k = fn x => fn y => x;
+            compose(k(1), k(false))(true)
+        }";
+        assert_eq!(eval!(src), "1")
+    }
+}
@@ -0,0 +1,34 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use super::Value;
+
+/// A single runtime scope
+/// Frames are reference counted so closures can capture the scope they were created in
+#[derive(Debug)]
+pub struct Frame<'a> {
+    vars: RefCell<HashMap<&'a str, Value<'a>>>,
+    parent: Option<Rc<Frame<'a>>>,
+}
+
+impl<'a> Frame<'a> {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self { vars: RefCell::new(HashMap::new()), parent: None })
+    }
+
+    /// Creates a new scope nested inside of `parent`
+    pub fn child(parent: &Rc<Self>) -> Rc<Self> {
+        Rc::new(Self { vars: RefCell::new(HashMap::new()), parent: Some(Rc::clone(parent)) })
+    }
+
+    pub fn define(&self, name: &'a str, value: Value<'a>) {
+        self.vars.borrow_mut().insert(name, value);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Value<'a>> {
+        match self.vars.borrow().get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref().and_then(|parent| parent.lookup(name)),
+        }
+    }
+}
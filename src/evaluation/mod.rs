@@ -0,0 +1,7 @@
+mod value;
+mod frame;
+mod evaluator;
+
+pub use value::Value;
+pub use evaluator::Evaluator;
+pub(crate) use frame::Frame;
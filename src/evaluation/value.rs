@@ -0,0 +1,60 @@
+use std::fmt::{self, Display, Formatter};
+use std::rc::Rc;
+use crate::parsing::{Expr, fmt_vec};
+use super::Frame;
+
+/// Runtime values produced by the evaluator
+/// Closures borrow their body from the ast so values cannot outlive the expression they were evaluated from
+#[derive(Clone, Debug)]
+pub enum Value<'a> {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Tuple(Vec<Value<'a>>),
+    /// Fields are kept sorted by name
+    Record(Vec<(&'a str, Value<'a>)>),
+    Closure { params: Vec<&'a str>, body: &'a Expr, env: Rc<Frame<'a>> },
+    Constructor { ctor: &'a str, arity: usize },
+    Adt { ctor: &'a str, args: Vec<Value<'a>> },
+}
+
+impl<'a> Value<'a> {
+    pub fn unit() -> Self { Self::Tuple(Vec::new()) }
+}
+
+/// Closures are compared by identity as functions have no decidable equality
+impl<'a> PartialEq for Value<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Int(x), Self::Int(y))       => x == y,
+            (Self::Float(x), Self::Float(y))   => x == y,
+            (Self::Bool(x), Self::Bool(y))     => x == y,
+            (Self::Str(x), Self::Str(y))       => x == y,
+            (Self::Tuple(xs), Self::Tuple(ys)) => xs == ys,
+            (Self::Record(xs), Self::Record(ys)) => xs == ys,
+            (Self::Adt { ctor: c, args: xs }, Self::Adt { ctor: d, args: ys }) => c == d && xs == ys,
+            (Self::Constructor { ctor: c, .. }, Self::Constructor { ctor: d, .. }) => c == d,
+            (Self::Closure { body: f, env: e, .. }, Self::Closure { body: g, env: d, .. }) =>
+                std::ptr::eq(*f, *g) && Rc::ptr_eq(e, d),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Display for Value<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Int(i)                => write!(f, "{}", i),
+            Self::Float(x)              => write!(f, "{:?}", x),
+            Self::Bool(b)               => write!(f, "{}", b),
+            Self::Str(s)                => write!(f, "\"{}\"", s),
+            Self::Tuple(xs)             => write!(f, "({})", fmt_vec(xs, ", ")),
+            Self::Record(fields)        => write!(f, "{{ {} }}", fields.iter().map(|(name, x)| format!("{} = {}", name, x)).collect::<Vec<_>>().join(", ")),
+            Self::Closure { params, .. } => write!(f, "<fn({})>", params.join(", ")),
+            Self::Constructor { ctor, .. } => write!(f, "<constructor {}>", ctor),
+            Self::Adt { ctor, args } if args.is_empty() => write!(f, "{}", ctor),
+            Self::Adt { ctor, args }     => write!(f, "{}({})", ctor, fmt_vec(args, ", ")),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+mod value;
+mod parser;
+
+pub use value::Json;
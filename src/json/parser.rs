@@ -0,0 +1,136 @@
+use super::Json;
+use std::iter::Peekable;
+use std::str::Chars;
+
+impl Json {
+    pub fn parse(src: &str) -> Result<Json, String> {
+        let mut parser = Parser { chars: src.chars().peekable() };
+        let json = parser.parse_value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(json),
+            Some(c) => Err(format!("Unexpected trailing character `{}`", c)),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('"') => self.parse_str().map(Json::Str),
+            Some('[') => {
+                self.chars.next();
+                let xs = self.parse_delimited(']', Self::parse_value)?;
+                Ok(Json::Array(xs))
+            }
+            Some('{') => {
+                self.chars.next();
+                let fields = self.parse_delimited('}', |parser| {
+                    parser.skip_whitespace();
+                    let key = parser.parse_str()?;
+                    parser.expect(':')?;
+                    Ok((key, parser.parse_value()?))
+                })?;
+                Ok(Json::Object(fields))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(format!("Unexpected character `{}`", c)),
+            None => Err("Unexpected end of input".to_owned()),
+        }
+    }
+
+    /// Parses comma separated items up to and including the closing character
+    fn parse_delimited<T>(&mut self, close: char, parse_fn: impl Fn(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let mut xs = vec![];
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&close) {
+            self.chars.next();
+            return Ok(xs)
+        }
+        loop {
+            xs.push(parse_fn(self)?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(c) if c == close => return Ok(xs),
+                _ => return Err(format!("Expected `,` or `{}`", close)),
+            }
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, json: Json) -> Result<Json, String> {
+        if literal.chars().all(|c| self.chars.next() == Some(c)) { Ok(json) }
+        else { Err(format!("Expected `{}`", literal)) }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let mut number = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || "+-.eE".contains(c)) { break }
+            number.push(c);
+            self.chars.next();
+        }
+        number.parse().map(Json::Number).map_err(|_| format!("Invalid number `{}`", number))
+    }
+
+    fn parse_str(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next().ok_or("Unterminated string")? {
+                '"' => return Ok(s),
+                '\\' => match self.chars.next().ok_or("Unterminated string")? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'u' => s.push(self.parse_unicode_escape()?),
+                    c => s.push(c),
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Parses the hex digits of a `\u` escape, combining surrogate pairs
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let hi = self.parse_hex()?;
+        let code = if (0xd800..0xdc00).contains(&hi) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let lo = self.parse_hex()?;
+            0x10000 + ((hi - 0xd800) << 10) + (lo.wrapping_sub(0xdc00) & 0x3ff)
+        } else { hi };
+        std::char::from_u32(code).ok_or_else(|| format!("Invalid unicode escape `{:x}`", code))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let digits = (0..4).filter_map(|_| self.chars.next()).collect::<String>();
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape `{}`", digits))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected `{}` found `{}`", expected, c)),
+            None => Err(format!("Expected `{}` found end of input", expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if !c.is_whitespace() { break }
+            self.chars.next();
+        }
+    }
+}
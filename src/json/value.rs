@@ -0,0 +1,137 @@
+use std::fmt::{self, Display, Formatter};
+
+/// A minimal JSON value used for diagnostics and the language server protocol
+/// Object fields keep their insertion order
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Self {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    }
+
+    /// Looks up a field of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self { Json::Str(s.to_owned()) }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self { Json::Str(s) }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self { Json::Bool(b) }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self { Json::Number(n as f64) }
+}
+
+impl<T> From<Option<T>> for Json where T : Into<Json> {
+    fn from(x: Option<T>) -> Self { x.map(Into::into).unwrap_or(Json::Null) }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(xs: Vec<Json>) -> Self { Json::Array(xs) }
+}
+
+/// Serializes compactly on a single line
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Json::Null       => write!(f, "null"),
+            Json::Bool(b)    => write!(f, "{}", b),
+            // Integers are written without a fractional part
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Json::Number(n)  => write!(f, "{}", n),
+            Json::Str(s)     => write_str(f, s),
+            Json::Array(xs)  => {
+                write!(f, "[")?;
+                for (i, x) in xs.iter().enumerate() {
+                    if i > 0 { write!(f, ",")? }
+                    write!(f, "{}", x)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 { write!(f, ",")? }
+                    write_str(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Quotes and escapes a string as a JSON string literal
+fn write_str(f: &mut Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"'  => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(Json::from("a\n\"b\"\\\u{1}").to_string(), r#""a\n\"b\"\\\u0001""#)
+    }
+
+    #[test]
+    fn serializes_object() {
+        let json = Json::object(vec![("id", Json::from(1)), ("xs", Json::from(vec![Json::Null, Json::Number(1.5)])), ("ok", Json::from(true))]);
+        assert_eq!(json.to_string(), r#"{"id":1,"xs":[null,1.5],"ok":true}"#)
+    }
+
+    #[test]
+    fn roundtrips() {
+        let src = r#"{"jsonrpc":"2.0","id":3,"params":{"text":"{ let x = \"\u00e9\"; x }","nested":[true,false,null,-2.5]}}"#;
+        let json = Json::parse(src).unwrap();
+        assert_eq!(json.get("params").and_then(|p| p.get("text")).and_then(Json::as_str), Some("{ let x = \"é\"; x }"));
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json)
+    }
+}
@@ -0,0 +1,238 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+use crate::error::{Error, ErrorCode};
+use crate::parsing::Span;
+use super::{Token, TokenKind};
+
+/// Produces the tokens of the source followed by an end of file token
+/// Whitespace and comments are skipped; the concrete syntax tree recovers them from the source as trivia
+pub struct Lexer<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Added to every position so files after the first in a source map get their own positions
+    offset: usize,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self::with_offset(src, 0)
+    }
+
+    pub fn with_offset(src: &'a str, offset: usize) -> Self {
+        Self { src, chars: src.char_indices().peekable(), offset, line: 1, col: 1 }
+    }
+
+    /// Lexes the entire source, collecting every error rather than stopping at the first
+    pub fn lex(mut self) -> Result<Vec<Token<'a>>, Vec<Error>> {
+        let mut tokens = vec![];
+        let mut errors = vec![];
+        while let Some(result) = self.next_token() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
+            }
+        }
+        tokens.push(Token { kind: TokenKind::EOF, lexeme: "", index: self.offset + self.src.len(), line: self.line, col: self.col });
+        if errors.is_empty() { Ok(tokens) } else { Err(errors) }
+    }
+
+    /// Returns None once the source is exhausted
+    fn next_token(&mut self) -> Option<Result<Token<'a>, Error>> {
+        loop {
+            let (start, line, col) = (self.pos(), self.line, self.col);
+            let kind = match self.bump()? {
+                c if c.is_whitespace() => continue,
+                '/' if self.eat('/') => {
+                    self.eat_while(|c| c != '\n');
+                    continue
+                }
+                '/' if self.eat('*') => {
+                    if self.skip_block_comment() { continue }
+                    return Some(Err(Error::new(Span::new(self.offset + start, self.offset + start + 2), "Unterminated block comment".to_owned()).with_code(ErrorCode::UnterminatedComment)))
+                }
+                '"' => {
+                    self.eat_while(|c| c != '"' && c != '\n');
+                    if !self.eat('"') {
+                        return Some(Err(Error::new(self.span_from(start), "Unterminated string".to_owned()).with_code(ErrorCode::UnterminatedString)))
+                    }
+                    TokenKind::Str
+                }
+                c if c.is_ascii_digit() => {
+                    self.eat_while(|c| c.is_ascii_digit());
+                    if self.eat_fraction() { TokenKind::Floating } else { TokenKind::Integral }
+                }
+                c if c.is_alphabetic() => {
+                    self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                    let lexeme = &self.src[start..self.pos()];
+                    let class = if c.is_uppercase() { TokenKind::Typename } else { TokenKind::Identifier };
+                    TokenKind::keyword(lexeme).unwrap_or(class)
+                }
+                '-' => if self.eat('>') { TokenKind::RArrow } else { TokenKind::Minus },
+                '=' => if self.eat('>') { TokenKind::RFArrow } else if self.eat('=') { TokenKind::DEqual } else { TokenKind::Equal },
+                '+' => if self.eat('+') { TokenKind::DPlus } else { TokenKind::Plus },
+                '*' => if self.eat('*') { TokenKind::DStar } else { TokenKind::Star },
+                '!' => if self.eat('=') { TokenKind::BangEqual } else { TokenKind::Bang },
+                '<' => if self.eat('=') { TokenKind::LTE } else { TokenKind::LT },
+                '>' => if self.eat('=') { TokenKind::GTE } else { TokenKind::GT },
+                ',' => TokenKind::Comma,
+                ';' => TokenKind::SemiColon,
+                ':' => TokenKind::Colon,
+                '\\' => TokenKind::Backslash,
+                '(' => TokenKind::LParen,
+                ')' => TokenKind::RParen,
+                '{' => TokenKind::LBrace,
+                '}' => TokenKind::RBrace,
+                '/' => TokenKind::Slash,
+                '.' => TokenKind::Dot,
+                '|' => TokenKind::Pipe,
+                '_' => TokenKind::Underscore,
+                '~' => TokenKind::Tilde,
+                c => return Some(Err(Error::new(self.span_from(start), format!("Unknown character `{}`", c)).with_code(ErrorCode::UnknownCharacter))),
+            };
+            return Some(Ok(Token { kind, lexeme: &self.src[start..self.pos()], index: self.offset + start, line, col }))
+        }
+    }
+
+    /// The span from `lo` up to the next character
+    fn span_from(&mut self, lo: usize) -> Span {
+        let hi = self.pos();
+        Span::new(self.offset + lo, self.offset + hi)
+    }
+
+    /// The byte offset of the next character within the source
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.src.len(), |&(i, _)| i)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let is_match = self.chars.peek().map(|&(_, c)| c) == Some(expected);
+        if is_match { self.bump(); }
+        is_match
+    }
+
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) {
+        while self.chars.peek().is_some_and(|&(_, c)| pred(c)) { self.bump(); }
+    }
+
+    /// Eats the fractional part and exponent following the digits of a number, returning whether there was either
+    /// The dot must be followed by a digit and the exponent by an optionally signed digit, otherwise they are left for the next token
+    fn eat_fraction(&mut self) -> bool {
+        let src = self.src.as_bytes();
+        let rest = &src[self.pos()..];
+        let mut is_float = false;
+        let mut len = 0;
+        if rest.len() > 1 && rest[0] == b'.' && rest[1].is_ascii_digit() {
+            is_float = true;
+            len = 1 + rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        }
+        if rest.len() > len + 1 && (rest[len] == b'e' || rest[len] == b'E') {
+            let sign = if rest[len + 1] == b'+' || rest[len + 1] == b'-' { 1 } else { 0 };
+            if rest.get(len + 1 + sign).is_some_and(u8::is_ascii_digit) {
+                is_float = true;
+                len += 1 + sign + rest[len + 1 + sign..].iter().take_while(|c| c.is_ascii_digit()).count();
+            }
+        }
+        // Every character consumed is ascii so bytes and chars coincide
+        (0..len).for_each(|_| { self.bump(); });
+        is_float
+    }
+
+    /// Skips to the end of a block comment, returning false if it is never closed
+    fn skip_block_comment(&mut self) -> bool {
+        loop {
+            match self.bump() {
+                Some('*') if self.eat('/') => return true,
+                Some(_) => {}
+                None => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        Lexer::new(src).lex().unwrap().iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn lexes_symbols_and_keywords() {
+        use TokenKind::*;
+        assert_eq!(kinds("let f = fn (x: Int) => x ** 2 != -1 -> Bool"), vec![
+            Let, Identifier, Equal, Fn, LParen, Identifier, Colon, Int, RParen, RFArrow,
+            Identifier, DStar, Integral, BangEqual, Minus, Integral, RArrow, Bool, EOF,
+        ]);
+        assert_eq!(kinds("a++b<=c>d==e"), vec![Identifier, DPlus, Identifier, LTE, Identifier, GT, Identifier, DEqual, Identifier, EOF]);
+    }
+
+    #[test]
+    fn lexes_floats() {
+        let tokens = Lexer::new("1.5 2e10 3E-2 4.25e+1 5.x 6e 7.").lex().unwrap();
+        let lexemes = tokens.iter().map(|token| (token.kind, token.lexeme)).collect::<Vec<_>>();
+        assert_eq!(lexemes, vec![
+            (TokenKind::Floating, "1.5"), (TokenKind::Floating, "2e10"), (TokenKind::Floating, "3E-2"), (TokenKind::Floating, "4.25e+1"),
+            (TokenKind::Integral, "5"), (TokenKind::Dot, "."), (TokenKind::Identifier, "x"),
+            (TokenKind::Integral, "6"), (TokenKind::Identifier, "e"),
+            (TokenKind::Integral, "7"), (TokenKind::Dot, "."), (TokenKind::EOF, ""),
+        ]);
+    }
+
+    #[test]
+    fn lexes_unicode_identifiers() {
+        let tokens = Lexer::new("let λx = café_2 + Ωmega").lex().unwrap();
+        let lexemes = tokens.iter().map(|token| (token.kind, token.lexeme)).collect::<Vec<_>>();
+        assert_eq!(lexemes, vec![
+            (TokenKind::Let, "let"), (TokenKind::Identifier, "λx"), (TokenKind::Equal, "="),
+            (TokenKind::Identifier, "café_2"), (TokenKind::Plus, "+"), (TokenKind::Typename, "Ωmega"), (TokenKind::EOF, ""),
+        ]);
+    }
+
+    #[test]
+    fn tracks_positions() {
+        let tokens = Lexer::new("λ\n  bc \"s\"").lex().unwrap();
+        assert_eq!((tokens[1].index, tokens[1].line, tokens[1].col), (5, 2, 3));
+        assert_eq!((tokens[2].lexeme, tokens[2].col), ("\"s\"", 6));
+        assert_eq!((tokens[3].kind, tokens[3].index), (TokenKind::EOF, 11));
+    }
+
+    #[test]
+    fn skips_comments() {
+        let tokens = Lexer::new("1 // one\n/* a\n b */ 2").lex().unwrap();
+        assert_eq!(tokens.iter().map(|token| token.kind).collect::<Vec<_>>(), vec![TokenKind::Integral, TokenKind::Integral, TokenKind::EOF]);
+        assert_eq!(tokens[1].line, 3);
+    }
+
+    #[test]
+    fn offsets_positions() {
+        let tokens = Lexer::with_offset("a b", 10).lex().unwrap();
+        assert_eq!(tokens.iter().map(|token| token.index).collect::<Vec<_>>(), vec![10, 12, 13]);
+        assert_eq!(Lexer::with_offset("#", 10).lex().unwrap_err()[0].span, Span::new(10, 11));
+    }
+
+    #[test]
+    fn collects_every_error() {
+        let errors = Lexer::new("1 # 2 $\n\"abc\n/* open").lex().unwrap_err();
+        let errors = errors.iter().map(|err| (err.code, err.span)).collect::<Vec<_>>();
+        assert_eq!(errors, vec![
+            (Some(ErrorCode::UnknownCharacter), Span::new(2, 3)),
+            (Some(ErrorCode::UnknownCharacter), Span::new(6, 7)),
+            (Some(ErrorCode::UnterminatedString), Span::new(8, 12)),
+            (Some(ErrorCode::UnterminatedComment), Span::new(13, 15)),
+        ]);
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt::{self, Display, Formatter};
+use crate::util::Dummy;
+use super::TokenKind;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub lexeme: &'a str,
+    /// Byte offset of the first character
+    pub index: usize,
+    /// One indexed line
+    pub line: usize,
+    /// One indexed column counting characters
+    pub col: usize,
+}
+
+impl<'a> Display for Token<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{}", self.lexeme) }
+}
+
+impl<'a> Dummy for Token<'a> {
+    fn dummy() -> Self {
+        Token {
+            kind: TokenKind::EOF,
+            lexeme: "",
+            index: usize::MAX,
+            line: usize::MAX,
+            col: usize::MAX,
+        }
+    }
+}
@@ -1,12 +1,99 @@
+use std::fmt::{self, Display, Formatter};
+
 #[derive(Copy, Clone, Debug, PartialEq, Hash, Eq)]
 pub enum TokenKind {
-    Plus, Minus, Star, Slash,
-    Number,
-    Eof,
-    Let, Var,
-    Bind,
-    Less, LessEqual, Greater, GreaterEqual,
-    Equal, BangEqual, Bang, DoubleEqual,
-    DPlus, TPlus,
-    Identifier
+    // Token classes
+    Integral, Floating, Identifier, Typename, Str,
+    // Symbols
+    RArrow, RFArrow, Comma, SemiColon, Colon, Backslash,
+    LParen, RParen, LBrace, RBrace,
+    Plus, DPlus, Minus, Star, DStar, Slash, Dot, Pipe, Underscore, Tilde,
+    Bang, BangEqual, Equal, DEqual, LT, LTE, GT, GTE,
+    // Keywords
+    Let, False, True, In, Fn, If, Then, Else, Type, Class, Instance, Match, As, Bool, Int, Float,
+    EOF,
+}
+
+impl TokenKind {
+    pub fn keyword(lexeme: &str) -> Option<Self> {
+        let kind = match lexeme {
+            "let"   => Self::Let,
+            "false" => Self::False,
+            "true"  => Self::True,
+            "in"    => Self::In,
+            "fn"    => Self::Fn,
+            "if"    => Self::If,
+            "then"  => Self::Then,
+            "else"  => Self::Else,
+            "type"  => Self::Type,
+            "class" => Self::Class,
+            "instance" => Self::Instance,
+            "match" => Self::Match,
+            "as"    => Self::As,
+            "Bool"  => Self::Bool,
+            "Int"   => Self::Int,
+            "Float" => Self::Float,
+            _       => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// Symbols and keywords display as their source text
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Integral   => "integer",
+            Self::Floating   => "floating point number",
+            Self::Identifier => "identifier",
+            Self::Typename   => "typename",
+            Self::Str        => "string",
+            Self::RArrow     => "->",
+            Self::RFArrow    => "=>",
+            Self::Comma      => ",",
+            Self::SemiColon  => ";",
+            Self::Colon      => ":",
+            Self::Backslash  => "\\",
+            Self::LParen     => "(",
+            Self::RParen     => ")",
+            Self::LBrace     => "{",
+            Self::RBrace     => "}",
+            Self::Plus       => "+",
+            Self::DPlus      => "++",
+            Self::Minus      => "-",
+            Self::Star       => "*",
+            Self::DStar      => "**",
+            Self::Slash      => "/",
+            Self::Dot        => ".",
+            Self::Pipe       => "|",
+            Self::Underscore => "_",
+            Self::Tilde      => "~",
+            Self::Bang       => "!",
+            Self::BangEqual  => "!=",
+            Self::Equal      => "=",
+            Self::DEqual     => "==",
+            Self::LT         => "<",
+            Self::LTE        => "<=",
+            Self::GT         => ">",
+            Self::GTE        => ">=",
+            Self::Let        => "let",
+            Self::False      => "false",
+            Self::True       => "true",
+            Self::In         => "in",
+            Self::Fn         => "fn",
+            Self::If         => "if",
+            Self::Then       => "then",
+            Self::Else       => "else",
+            Self::Type       => "type",
+            Self::Class      => "class",
+            Self::Instance   => "instance",
+            Self::Match      => "match",
+            Self::As         => "as",
+            Self::Bool       => "Bool",
+            Self::Int        => "Int",
+            Self::Float      => "Float",
+            Self::EOF        => "end of file",
+        };
+        write!(f, "{}", s)
+    }
 }
@@ -1,7 +1,5 @@
-#![feature(box_syntax, box_patterns)]
-
-#[macro_use]
-extern crate colour;
+#![feature(box_patterns)]
+#![cfg_attr(test, feature(test))]
 
 mod parsing;
 mod lexing;
@@ -9,81 +7,52 @@ mod error;
 mod util;
 mod macros;
 mod typechecking;
+mod evaluation;
+mod analysis;
+mod json;
+mod printing;
+mod cst;
+mod source;
+mod driver;
 
 
-use regexlexer::{Lexer, LexSyntax};
-use lexing::gen_syntax;
-use typechecking::Typechecker;
+use lexing::Lexer;
 use util::Counter;
 
-pub use error::{Error, Formatter};
-pub use parsing::{Parser, Expr, ExprKind};
-pub use regexlexer::{Token, TokenKind};
-pub use typechecking::{Ty, TyKind};
-
-/// Generate ast using the default syntax provided from this crate
-pub fn generate_ast<'a>(src: &'a str) -> Result<(Ty, Expr), Vec<Error>> {
-    generate_ast_with_syntax(src, &gen_syntax())
+pub use error::{Error, ErrorCode, Severity, Label, Formatter, ErrorFormat};
+pub use parsing::{Parser, Expr, ExprKind, Span};
+pub use lexing::{Token, TokenKind};
+pub use typechecking::{Ty, TyKind, TypecheckMode};
+pub use evaluation::{Evaluator, Value};
+pub use analysis::{hover, definition};
+pub use json::Json;
+pub use printing::Printer;
+pub use cst::{SyntaxNode, SyntaxToken, SyntaxElement, NodeKind, Trivia, TriviaKind};
+pub use source::{SourceMap, SourceFile, FileId, Loc};
+pub use driver::{Session, Compilation, Stage};
+
+/// Lexes, parses and typechecks a single source
+pub fn generate_ast(src: &str) -> Result<(Ty, Expr), Vec<Error>> {
+    let mut session = Session::new();
+    let file = session.add_file("", src);
+    session.compile(file).into_result()
 }
 
-pub fn generate_ast_with_syntax<'a, 'b>(src: &'a str, syntax: &'b LexSyntax) -> Result<(Ty, Expr), Vec<Error>> {
-    let lexer = Lexer::new(src, &syntax);
-    let tokens = match lexer.lex() {
-        Ok(ts) => ts,
-        Err(errors) => {
-            eprintln!("{}", errors.join("\n"));
-            std::process::exit(1);
-        }
-    };
-
+/// Parses without typechecking, which is all formatting requires
+pub fn parse(src: &str) -> Result<Expr, Vec<Error>> {
+    let tokens = Lexer::new(src).lex()?;
     let mut gen = Counter::new();
-    let mut parser = Parser::new(&tokens, &mut gen);
-    let mut expr = parser.parse()?;
-    let mut typechecker = Typechecker::new(&mut gen);
-    let ty = typechecker.typecheck(&mut expr)?;
-
-    Ok((ty, expr))
+    Parser::new(&tokens, &mut gen).parse()
 }
 
-pub fn generate_ast_with_err_handling(src: &str) -> (Ty, Expr) {
-    let syntax = gen_syntax();
-    let lexer = Lexer::new(src, &syntax);
-    let tokens = match lexer.lex() {
-        Ok(ts) => ts,
-        Err(errors) => {
-            eprintln!("{}", errors.join("\n"));
-            std::process::exit(1);
-        }
-    };
-
-    // println!("{:?}", tokens);
+/// Parses into a lossless concrete syntax tree that keeps comments and whitespace
+pub fn parse_cst(src: &str) -> Result<SyntaxNode, Vec<Error>> {
+    let tokens = Lexer::new(src).lex()?;
     let mut gen = Counter::new();
-    let mut parser = Parser::new(&tokens, &mut gen);
-    let formatter = Formatter::new(src);
-
-    let mut ast = match parser.parse() {
-        Ok(ast) => ast,
-        Err(errors) => {
-            formatter.write(errors);
-            std::process::exit(1);
-        }
-    };
-
-    let mut typechecker = Typechecker::new(&mut gen);
-    let ty = match typechecker.typecheck(&mut ast) {
-        Ok(ast) => ast,
-        Err(errors) => {
-            formatter.write(errors);
-            std::process::exit(1);
-        }
-    };
-
-    (ty, ast)
+    let expr = Parser::new(&tokens, &mut gen).parse()?;
+    Ok(cst::build(src, &tokens, &expr))
 }
 
-
-
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use parserlib::{generate_ast, hover, definition, Json, Error, Expr, Severity, SourceFile, FileId, Loc, Span};
+
+/// Language server speaking JSON-RPC over stdio
+/// Supports diagnostics, hover and go to definition with full document synchronisation
+fn main() {
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let mut server = Server::new();
+    while let Some(msg) = read_message(&mut stdin) {
+        let msg = match Json::parse(&msg) {
+            Ok(msg) => msg,
+            Err(err) => {
+                eprintln!("Failed to parse message: {}", err);
+                continue
+            }
+        };
+        if server.handle(&msg) == Control::Exit { break }
+    }
+}
+
+#[derive(PartialEq)]
+enum Control {
+    Continue,
+    Exit,
+}
+
+struct Server {
+    documents: HashMap<String, Document>,
+}
+
+/// An open document along with its typechecked tree, or the errors that prevented one
+/// The document is only analysed when it changes so requests are answered from the tree
+struct Document {
+    file: SourceFile,
+    ast: Result<Expr, Vec<Error>>,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self { documents: HashMap::new() }
+    }
+
+    fn handle(&mut self, msg: &Json) -> Control {
+        let method = msg.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = msg.get("params").cloned().unwrap_or(Json::Null);
+        let id = match msg.get("id") {
+            Some(id) => id.clone(),
+            // Notifications have no id and receive no response
+            None => {
+                match method {
+                    "textDocument/didOpen" => {
+                        let document = params.get("textDocument");
+                        let uri = document.and_then(|d| d.get("uri")).and_then(Json::as_str);
+                        let text = document.and_then(|d| d.get("text")).and_then(Json::as_str);
+                        if let (Some(uri), Some(text)) = (uri, text) { self.update(uri, text) }
+                    }
+                    "textDocument/didChange" => {
+                        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str);
+                        // Only full synchronisation is advertised so the last change holds the entire text
+                        let text = match params.get("contentChanges") {
+                            Some(Json::Array(changes)) => changes.last().and_then(|c| c.get("text")).and_then(Json::as_str),
+                            _ => None,
+                        };
+                        if let (Some(uri), Some(text)) = (uri, text) { self.update(uri, text) }
+                    }
+                    "textDocument/didClose" => {
+                        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str);
+                        if let Some(uri) = uri { self.documents.remove(uri); }
+                    }
+                    "exit" => return Control::Exit,
+                    _ => {}
+                }
+                return Control::Continue
+            }
+        };
+
+        let result = match method {
+            "initialize" => Json::object(vec![
+                ("capabilities", Json::object(vec![
+                    ("textDocumentSync", Json::from(1)),
+                    ("hoverProvider", Json::from(true)),
+                    ("definitionProvider", Json::from(true)),
+                ])),
+                ("serverInfo", Json::object(vec![("name", Json::from("parserlsp"))])),
+            ]),
+            "shutdown" => Json::Null,
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/definition" => self.definition(&params),
+            _ => {
+                respond(Json::object(vec![
+                    ("jsonrpc", Json::from("2.0")),
+                    ("id", id),
+                    ("error", Json::object(vec![("code", Json::Number(-32601.0)), ("message", Json::from(format!("Unknown method `{}`", method)))])),
+                ]));
+                return Control::Continue
+            }
+        };
+        respond(Json::object(vec![("jsonrpc", Json::from("2.0")), ("id", id), ("result", result)]));
+        Control::Continue
+    }
+
+    fn update(&mut self, uri: &str, text: &str) {
+        // Each document is analysed on its own so its positions start at zero
+        let file = SourceFile::new(FileId(0), uri.to_owned(), text.to_owned(), 0);
+        let ast = generate_ast(&file.src).map(|(_, expr)| expr);
+        self.publish_diagnostics(uri, &file, ast.as_ref().err().map_or(&[], Vec::as_slice));
+        self.documents.insert(uri.to_owned(), Document { file, ast });
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        self.query(params, |file, expr, pos| hover(expr, pos).map(|(span, ty)| Json::object(vec![
+            ("contents", Json::object(vec![("kind", Json::from("plaintext")), ("value", Json::from(ty))])),
+            ("range", range(file, span)),
+        ])))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let uri = params.get("textDocument").and_then(|d| d.get("uri")).cloned().unwrap_or(Json::Null);
+        self.query(params, |file, expr, pos| definition(expr, pos).map(|span| Json::object(vec![
+            ("uri", uri.clone()),
+            ("range", range(file, span)),
+        ])))
+    }
+
+    /// Runs the query on the typechecked document at the requested position
+    /// Documents with errors have no tree to query and give null
+    fn query(&self, params: &Json, f: impl Fn(&SourceFile, &Expr, usize) -> Option<Json>) -> Json {
+        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str);
+        let (file, expr) = match uri.and_then(|uri| self.documents.get(uri)) {
+            Some(Document { file, ast: Ok(expr) }) => (file, expr),
+            _ => return Json::Null,
+        };
+        let position = params.get("position");
+        let line = position.and_then(|p| p.get("line")).and_then(Json::as_usize).unwrap_or(0);
+        let character = position.and_then(|p| p.get("character")).and_then(Json::as_usize).unwrap_or(0);
+        f(file, expr, file.utf16_pos(Loc::new(line, character))).unwrap_or(Json::Null)
+    }
+
+    fn publish_diagnostics(&self, uri: &str, file: &SourceFile, errors: &[Error]) {
+        let diagnostics = errors.iter().map(|error| {
+            let message = match &error.help {
+                Some(help) => format!("{}\nhelp: {}", error.msg, help),
+                None => error.msg.clone(),
+            };
+            let related = error.labels.iter().map(|label| Json::object(vec![
+                ("location", Json::object(vec![("uri", Json::from(uri)), ("range", range(file, label.span))])),
+                ("message", Json::from(label.msg.as_str())),
+            ])).collect::<Vec<_>>();
+            Json::object(vec![
+                ("range", range(file, error.span)),
+                ("severity", Json::from(if error.severity == Severity::Error { 1 } else { 2 })),
+                ("code", Json::from(error.code.map(|code| code.to_string()))),
+                ("source", Json::from("parserlsp")),
+                ("message", Json::from(message)),
+                ("relatedInformation", Json::from(related)),
+            ])
+        }).collect::<Vec<_>>();
+        respond(Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from("textDocument/publishDiagnostics")),
+            ("params", Json::object(vec![("uri", Json::from(uri)), ("diagnostics", Json::from(diagnostics))])),
+        ]));
+    }
+}
+
+/// Reads a single message framed by a `Content-Length` header
+fn read_message(input: &mut impl BufRead) -> Option<String> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 { return None }
+        let header = header.trim_end();
+        if header.is_empty() { break }
+        let mut parts = header.splitn(2, ':');
+        if parts.next() == Some("Content-Length") { len = parts.next().and_then(|n| n.trim().parse().ok()) }
+    }
+    let mut buf = vec![0; len?];
+    input.read_exact(&mut buf).ok()?;
+    String::from_utf8(buf).ok()
+}
+
+fn respond(msg: Json) {
+    let msg = msg.to_string();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    write!(stdout, "Content-Length: {}\r\n\r\n{}", msg.len(), msg).and_then(|_| stdout.flush()).expect("Failed to write to stdout");
+}
+
+/// LSP positions are zero indexed lines and characters counted in utf-16 code units
+fn range(file: &SourceFile, span: Span) -> Json {
+    let position = |loc: Loc| Json::object(vec![("line", Json::from(loc.line)), ("character", Json::from(loc.col))]);
+    Json::object(vec![("start", position(file.utf16_loc(span.lo))), ("end", position(file.utf16_loc(span.hi)))])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_framed_messages() {
+        let input = "Content-Length: 2\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{}Content-Length: 9\r\n\r\n[1, \"λ\"]";
+        let mut input = io::Cursor::new(input.as_bytes());
+        assert_eq!(read_message(&mut input).as_deref(), Some("{}"));
+        // The length counts bytes rather than characters
+        assert_eq!(read_message(&mut input).as_deref(), Some("[1, \"λ\"]"));
+        assert_eq!(read_message(&mut input), None);
+    }
+
+    #[test]
+    fn ranges_count_utf16_code_units() {
+        let file = SourceFile::new(FileId(0), String::new(), "\"😀\" + x\nλ".to_owned(), 0);
+        let position = |line: usize, character: usize| Json::object(vec![("line", Json::from(line)), ("character", Json::from(character))]);
+        assert_eq!(range(&file, Span::new(6, 10)), Json::object(vec![("start", position(0, 4)), ("end", position(0, 8))]));
+    }
+}
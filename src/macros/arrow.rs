@@ -3,13 +3,13 @@
 macro_rules! arrow {
     ( ($e:expr $( => $es:expr )* ) ) => { arrow!( $e $( => $es )*) }; // pattern to match redundant parens (i.e. nothing follows it)
     ( ($e:expr $( => $es:expr )* ) $( => $rest:expr )+ ) => {
-        crate::typechecking::TyKind::Arrow(
-            box arrow!($e $( => $es )* ),
-            box arrow!( $( $rest ) => + )
+        $crate::typechecking::TyKind::Arrow(
+            Box::new(arrow!($e $( => $es )* )),
+            Box::new(arrow!( $( $rest ) => + ))
         ).to_ty()
     };
     ( $e:expr ) => { $e };
-    ( $e:expr $( => $es:expr )+ ) => { crate::typechecking::TyKind::Arrow(box $e, box arrow!( $( $es ) => + ) ).to_ty() };
+    ( $e:expr $( => $es:expr )+ ) => { $crate::typechecking::TyKind::Arrow(Box::new($e), Box::new(arrow!( $( $es ) => + )) ).to_ty() };
 }
 
 #[cfg(test)]
@@ -25,15 +25,15 @@ mod test {
     #[test]
     fn double_arrow() {
         let t = arrow!(TyKind::F64.to_ty() => TyKind::Bool.to_ty());
-        assert_eq!(t, TyKind::Arrow(box TyKind::F64.to_ty(), box TyKind::Bool.to_ty()).to_ty())
+        assert_eq!(t, TyKind::Arrow(Box::new(TyKind::F64.to_ty()), Box::new(TyKind::Bool.to_ty())).to_ty())
     }
 
     #[test]
     fn redundant_paren_arrow() {
         let t = arrow!( (TyKind::F64.to_ty() => TyKind::Bool.to_ty()) );
         let expected = TyKind::Arrow(
-            box TyKind::F64.to_ty(),
-            box TyKind::Bool.to_ty(),
+            Box::new(TyKind::F64.to_ty()),
+            Box::new(TyKind::Bool.to_ty()),
         ).to_ty();
         assert_eq!(t, expected);
     }
@@ -42,11 +42,11 @@ mod test {
     fn paren_arrow() {
         let t = arrow!((TyKind::Bool.to_ty() => TyKind::F64.to_ty()) => TyKind::I64.to_ty());
         let expected = TyKind::Arrow(
-            box TyKind::Arrow(
-                box TyKind::Bool.to_ty(),
-                box TyKind::F64.to_ty()
-            ).to_ty(),
-            box TyKind::I64.to_ty(),
+            Box::new(TyKind::Arrow(
+                Box::new(TyKind::Bool.to_ty()),
+                Box::new(TyKind::F64.to_ty())
+            ).to_ty()),
+            Box::new(TyKind::I64.to_ty()),
         ).to_ty();
         assert_eq!(t, expected);
     }
@@ -58,11 +58,11 @@ mod test {
         let t2 = TyKind::Bool.to_ty();
         let t = arrow!(t0 => t1 => t2);
         let expected = TyKind::Arrow(
-            box TyKind::F64.to_ty(),
-            box TyKind::Arrow(
-                box TyKind::I64.to_ty(),
-                box TyKind::Bool.to_ty()
-            ).to_ty()
+            Box::new(TyKind::F64.to_ty()),
+            Box::new(TyKind::Arrow(
+                Box::new(TyKind::I64.to_ty()),
+                Box::new(TyKind::Bool.to_ty())
+            ).to_ty())
         ).to_ty();
         assert_eq!(t, expected);
     }
@@ -1,7 +1,8 @@
-use regexlexer::TokenKind;
+use crate::lexing::TokenKind;
 use std::fmt::{self, Display, Formatter, Debug};
-use crate::typechecking::Ty;
-use crate::parsing::Span;
+use crate::typechecking::{Ty, Predicate};
+use crate::parsing::{Span, Pattern};
+use crate::printing::Printer;
 
 #[derive(PartialEq, Clone)]
 pub struct Expr {
@@ -15,6 +16,42 @@ impl Expr {
     pub fn new(span: Span, kind: ExprKind, ty: Ty, node_id: u64) -> Self {
         Expr { span, kind, ty, node_id }
     }
+
+    /// The direct subexpressions in source order
+    pub fn children(&self) -> Vec<&Expr> {
+        match &self.kind {
+            ExprKind::Unary { expr, .. } | ExprKind::Grouping { expr } | ExprKind::Cast { expr, .. } | ExprKind::Access { expr, .. } => vec![expr],
+            ExprKind::Binary { left, right, .. } => vec![left, right],
+            ExprKind::Let { bound, .. } => vec![bound],
+            ExprKind::LetIn { bound, body, .. } => vec![bound, body],
+            ExprKind::Block { exprs, .. } => exprs.iter().collect(),
+            ExprKind::Lambda { body, .. } => vec![body],
+            ExprKind::App { f, args } => std::iter::once(&**f).chain(args).collect(),
+            ExprKind::Tuple { elems } => elems.iter().collect(),
+            ExprKind::Record { fields } => fields.iter().map(|field| &field.value).collect(),
+            ExprKind::Update { record, fields } => std::iter::once(&**record).chain(fields.iter().map(|field| &field.value)).collect(),
+            ExprKind::If { cond, then, otherwise } => vec![&**cond, then].into_iter().chain(otherwise.as_deref()).collect(),
+            ExprKind::Match { scrutinee, arms } => std::iter::once(&**scrutinee).chain(arms.iter().map(|arm| &arm.body)).collect(),
+            ExprKind::Integral { .. } | ExprKind::Floating { .. } | ExprKind::Bool { .. } | ExprKind::Id { .. } | ExprKind::Str { .. } | ExprKind::TypeDef { .. }
+                | ExprKind::Class { .. } | ExprKind::Instance { .. } => vec![],
+        }
+    }
+
+    pub fn is_lambda(&self) -> bool {
+        match &self.kind {
+            ExprKind::Lambda { .. } => true,
+            ExprKind::Grouping { expr } => expr.is_lambda(),
+            _ => false,
+        }
+    }
+
+    /// Whether this is a let binding a lambda, which may refer to itself
+    pub fn is_lambda_binding(&self) -> bool {
+        match &self.kind {
+            ExprKind::Let { bound, .. } => bound.is_lambda(),
+            _ => false,
+        }
+    }
 }
 
 impl Display for Expr {
@@ -46,64 +83,154 @@ impl Display for Binder {
 
 impl Debug for Binder { fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{}", self) } }
 
+/// A single constructor of an algebraic data type declaration
+#[derive(Clone, PartialEq)]
+pub struct Variant {
+    pub span: Span,
+    pub name: String,
+    pub fields: Vec<Ty>,
+}
+
+impl Variant {
+    pub fn new(span: Span, name: String, fields: Vec<Ty>) -> Self {
+        Self { span, name, fields }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.fields.is_empty() { write!(f, "{}", self.name) }
+        else { write!(f, "{}({})", self.name, fmt_vec(&self.fields, ", ")) }
+    }
+}
+
+impl Debug for Variant { fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{}", self) } }
+
+/// A field of a record literal or update, `name = value`
+#[derive(Clone, PartialEq)]
+pub struct Field {
+    pub span: Span,
+    pub name: String,
+    pub value: Expr,
+}
+
+impl Field {
+    pub fn new(span: Span, name: String, value: Expr) -> Self {
+        Self { span, name, value }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{} = {}", self.name, self.value) }
+}
+
+impl Debug for Field {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "[{} = {:?}]", self.name, self.value) }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Arm {
+    pub span: Span,
+    pub pat: Pattern,
+    pub body: Expr,
+}
+
+impl Arm {
+    pub fn new(span: Span, pat: Pattern, body: Expr) -> Self {
+        Self { span, pat, body }
+    }
+}
+
+impl Display for Arm {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{} => {}", self.pat, self.body) }
+}
+
+impl Debug for Arm {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "[{} => {:?}]", self.pat, self.body) }
+}
+
 #[derive(Clone, PartialEq)]
 pub enum ExprKind {
-    Unary { op: TokenKind, expr: Box<Expr> },
+    Unary { op: TokenKind, op_span: Span, expr: Box<Expr> },
     Integral { value: i64 },
+    Floating { value: f64 },
     Bool { b: bool },
     Id { name: String },
     Str { string: String },
-    Binary { op: TokenKind, left: Box<Expr>, right: Box<Expr> },
+    Binary { op: TokenKind, op_span: Span, left: Box<Expr>, right: Box<Expr> },
     Grouping { expr: Box<Expr> },
     Let { binder: Binder, bound: Box<Expr> },
+    /// `let x = bound in body` where the binding is only in scope in the body
+    LetIn { binder: Binder, bound: Box<Expr>, body: Box<Expr> },
     Block { exprs: Vec<Expr>, suppressed: bool },
-    Lambda { params: Vec<Binder>, ret: Ty, body: Box<Expr> },
+    /// The return type is only present if it is annotated
+    Lambda { params: Vec<Binder>, ret: Option<Ty>, body: Box<Expr> },
     App { f: Box<Expr>, args: Vec<Expr> },
     Tuple { elems: Vec<Expr> },
+    Record { fields: Vec<Field> },
+    /// `{ record | x = value }` copies the record with the given fields replaced
+    Update { record: Box<Expr>, fields: Vec<Field> },
+    /// `expr.field`
+    Access { expr: Box<Expr>, field: String, field_span: Span },
+    If { cond: Box<Expr>, then: Box<Expr>, otherwise: Option<Box<Expr>> },
+    TypeDef { name: String, params: Vec<String>, variants: Vec<Variant> },
+    Match { scrutinee: Box<Expr>, arms: Vec<Arm> },
+    Cast { expr: Box<Expr>, ty: Ty },
+    Class { name: String, param: String },
+    Instance { context: Vec<Predicate>, head: Predicate },
+}
+
+fn fmt_typedef(fmt: &mut Formatter, name: &str, params: &[String], variants: &[Variant]) -> fmt::Result {
+    write!(fmt, "type {}", name)?;
+    if !params.is_empty() { write!(fmt, "<{}>", params.join(", "))? }
+    write!(fmt, " = {}", fmt_vec(variants, " | "))
 }
 
-pub fn fmt_vec<T>(xs: &Vec<T>, sep: &str) -> String where T : Display {
+pub fn fmt_vec<T>(xs: &[T], sep: &str) -> String where T : Display {
     xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(sep)
 }
 
-pub fn fmt_vec_debug<T>(xs: &Vec<T>, sep: &str) -> String where T : Debug {
+pub fn fmt_vec_debug<T>(xs: &[T], sep: &str) -> String where T : Debug {
     xs.iter().map(|x| format!("{:?}", x)).collect::<Vec<_>>().join(sep)
 }
 
+/// Prints the expression as source on a single line
 impl Display for ExprKind {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
-        match self {
-            Self::Unary { op, expr }           => write!(fmt, "{}{}", op, expr),
-            Self::Integral { value }           => write!(fmt, "{}", value),
-            Self::Id { name }                  => write!(fmt, "{}", name),
-            Self::Binary { op, left, right }   => write!(fmt, "{} {} {}", left, op, right),
-            Self::Grouping { expr }            => write!(fmt, "({})", expr),
-            Self::Bool { b }                   => write!(fmt, "{}", b),
-            Self::Str { string }               => write!(fmt, "{}", string),
-            Self::Let { binder, bound }        => write!(fmt, "let {} = {}", binder, bound),
-            Self::Block { exprs, suppressed }  => write!(fmt, "{{ {}{} }}", fmt_vec(exprs, "; "), if *suppressed { ";" } else {""}),
-            Self::Lambda { params, ret, body } => write!(fmt, "fn ({}) -> {} => {}", fmt_vec(params, ", "), ret, body),
-            Self::App { f, args }              => write!(fmt, "{}({})", f, fmt_vec(args, ", ")),
-            Self::Tuple { elems }              => write!(fmt, "({})", fmt_vec(elems, ", ")),
-        }
+        write!(fmt, "{}", Printer::new(usize::MAX).print_kind(self))
     }
 }
 
 impl Debug for ExprKind {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         match self {
-            Self::Unary { op, expr }           => write!(fmt, "({}{:?})", op, expr),
+            Self::Unary { op, expr, .. }       => write!(fmt, "({}{:?})", op, expr),
             Self::Integral { value }           => write!(fmt, "{}", value),
+            Self::Floating { value }           => write!(fmt, "{:?}", value),
             Self::Id { name }                  => write!(fmt, "{}", name),
-            Self::Binary { op, left, right }   => write!(fmt, "({} {:?} {:?})", op, left, right),
+            Self::Binary { op, left, right, .. } => write!(fmt, "({} {:?} {:?})", op, left, right),
             Self::Grouping { expr }            => write!(fmt, "{:?}", expr),
             Self::Bool { b }                   => write!(fmt, "{}", b),
             Self::Str { string }               => write!(fmt, "{}", string),
             Self::Let { binder, bound }        => write!(fmt, "(let [{} = {:?}])", binder, bound),
+            Self::LetIn { binder, bound, body } => write!(fmt, "(let [{} = {:?}] in {:?})", binder, bound, body),
             Self::Block { exprs, suppressed }  => write!(fmt, "{{ {}{} }}", fmt_vec_debug(exprs, "; "), if *suppressed { ";" } else {""}),
-            Self::Lambda { params, ret, body } => write!(fmt, "(lambda ({}) -> {} => {:?})", fmt_vec_debug(params, ", "), ret, body),
+            Self::Lambda { params, ret: Some(ret), body } => write!(fmt, "(lambda ({}) -> {} => {:?})", fmt_vec_debug(params, ", "), ret, body),
+            Self::Lambda { params, ret: None, body } => write!(fmt, "(lambda ({}) => {:?})", fmt_vec_debug(params, ", "), body),
             Self::App { f, args }              => write!(fmt, "({} {})", f, fmt_vec_debug(args, " ")),
             Self::Tuple { elems }              => write!(fmt, "({})", fmt_vec_debug(elems, ", ")),
+            Self::Record { fields }            => write!(fmt, "(record {})", fmt_vec_debug(fields, " ")),
+            Self::Update { record, fields }    => write!(fmt, "(update {:?} {})", record, fmt_vec_debug(fields, " ")),
+            Self::Access { expr, field, .. }   => write!(fmt, "(. {:?} {})", expr, field),
+            Self::If { cond, then, otherwise } => match otherwise {
+                Some(otherwise) => write!(fmt, "(if {:?} {:?} {:?})", cond, then, otherwise),
+                None            => write!(fmt, "(if {:?} {:?})", cond, then),
+            },
+            Self::TypeDef { name, params, variants } => fmt_typedef(fmt, name, params, variants),
+            Self::Match { scrutinee, arms }    => write!(fmt, "(match {:?} {})", scrutinee, fmt_vec_debug(arms, " ")),
+            Self::Cast { expr, ty }            => write!(fmt, "(as {:?} {})", expr, ty),
+            Self::Class { name, param }        => write!(fmt, "(class {} {})", name, param),
+            Self::Instance { context, head }   => write!(fmt, "(instance ({}) {})", fmt_vec(context, ", "), head),
         }
     }
 
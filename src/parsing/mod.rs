@@ -2,11 +2,14 @@ mod parser;
 mod expr;
 mod precedence;
 mod span;
+mod pattern;
 pub mod parselets;
 
 pub use parser::Parser;
-pub use expr::{Expr, ExprKind, Binder};
-pub(crate) use span::Span;
+pub use expr::{Expr, ExprKind, Binder, Variant, Arm, Field};
+pub use pattern::{Pattern, PatternKind};
+pub(crate) use expr::fmt_vec;
+pub use span::Span;
 pub(crate) use precedence::Precedence;
 
 
@@ -0,0 +1,10 @@
+use crate::parsing::{Parser, Expr, ExprKind};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+pub(crate) fn parse_access<'a>(parser: &mut Parser<'a>, left: Expr, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let token = parser.expect(TokenKind::Identifier)?;
+    let exprkind = ExprKind::Access { expr: Box::new(left), field: token.lexeme.to_owned(), field_span: Parser::token_span(token) };
+    Ok((exprkind, None))
+}
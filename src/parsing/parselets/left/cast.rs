@@ -0,0 +1,11 @@
+use crate::parsing::{Parser, Expr, ExprKind};
+use crate::lexing::Token;
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+/// The target type is checked to be numeric by the typechecker
+pub(crate) fn parse_cast<'a>(parser: &mut Parser<'a>, left: Expr, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let ty = parser.parse_type()?;
+    let exprkind = ExprKind::Cast { expr: Box::new(left), ty };
+    Ok((exprkind, None))
+}
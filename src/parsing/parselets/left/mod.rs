@@ -1,5 +1,9 @@
 mod binary;
 mod application;
+mod cast;
+mod access;
 
 pub(crate) use binary::parse_binary;
 pub(crate) use application::parse_application;
+pub(crate) use cast::parse_cast;
+pub(crate) use access::parse_access;
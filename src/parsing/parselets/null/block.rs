@@ -1,16 +1,25 @@
-use crate::parsing::{Parser, ExprKind, Precedence};
-use regexlexer::{Token, TokenKind};
+use crate::parsing::{Parser, ExprKind};
+use crate::lexing::{Token, TokenKind};
 use crate::error::Error;
 use crate::typechecking::Ty;
+use super::{is_record, parse_record, parse_update};
 
-pub(crate) fn parse_block(parser: &mut Parser, _token: Token) -> Result<(ExprKind, Option<Ty>), Error> {
+pub(crate) fn parse_block<'a>(parser: &mut Parser<'a>, token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    if is_record(parser) { return parse_record(parser, token) }
     let mut exprs = vec![];
     let mut suppressed = false;
+    let mut leading = true;
     loop {
         // If a } is matched, then either the block is empty or the final expression had a semicolon
         // And hence suppressed should be set
         if parser.matches(TokenKind::RBrace) { suppressed = true; break; }
-        exprs.push(parser.parse_expression(Precedence::ZERO)?);
+        // Statements that fail to parse are recorded by the parser and skipped
+        if let Some(expr) = parser.parse_statement(leading) {
+            // A `|` after the leading expression makes this a record update
+            if leading && parser.matches(TokenKind::Pipe) { return parse_update(parser, expr) }
+            exprs.push(expr)
+        }
+        leading = false;
         if !parser.matches(TokenKind::SemiColon) { break }
     };
 
@@ -1,6 +1,6 @@
 use crate::parsing::{Parser, ExprKind};
 use crate::typechecking::{Ty, TyKind};
-use regexlexer::Token;
+use crate::lexing::Token;
 use crate::error::Error;
 
 pub(crate) fn parse_bool<'a>(parser: &mut Parser<'a>, token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
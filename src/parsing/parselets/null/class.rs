@@ -0,0 +1,11 @@
+use crate::parsing::{Parser, ExprKind};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+/// Parses class declarations of the form `class Num a`
+pub(crate) fn parse_class<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let name = parser.expect(TokenKind::Typename)?.lexeme.to_owned();
+    let param = parser.expect(TokenKind::Identifier)?.lexeme.to_owned();
+    Ok((ExprKind::Class { name, param }, None))
+}
@@ -0,0 +1,16 @@
+use crate::parsing::{Parser, ExprKind, Precedence};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+pub(crate) fn parse_if<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let cond = Box::new(parser.parse_expression(Precedence::ZERO)?);
+    parser.expect(TokenKind::Then)?;
+    let then = Box::new(parser.parse_expression(Precedence::ZERO)?);
+    // A dangling else binds to the innermost if
+    let otherwise = if parser.matches(TokenKind::Else) {
+        Some(Box::new(parser.parse_expression(Precedence::ZERO)?))
+    } else { None };
+    let kind = ExprKind::If { cond, then, otherwise };
+    Ok((kind, None))
+}
@@ -0,0 +1,11 @@
+use crate::parsing::{Parser, ExprKind};
+use crate::typechecking::{Ty, TyKind};
+use crate::lexing::Token;
+use crate::error::Error;
+
+pub(crate) fn parse_floating<'a>(parser: &mut Parser<'a>, token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let value = Parser::floating_value(token)?;
+    let kind = ExprKind::Floating { value };
+    let ty = Ty::new(parser.get_single_span(), TyKind::F64);
+    Ok((kind, Some(ty)))
+}
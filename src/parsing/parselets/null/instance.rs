@@ -0,0 +1,19 @@
+use crate::parsing::{Parser, ExprKind};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+/// Parses instance declarations of the form `instance Num Int` or `instance (Eq a, Eq b) => Eq Pair<a, b>`
+/// A context of a single predicate does not need parentheses
+pub(crate) fn parse_instance<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    // Predicates start with a class name so a parenthesis can only open a context
+    let (context, head) = if parser.matches(TokenKind::LParen) {
+        let context = parser.parse_tuple(Parser::parse_predicate)?.0;
+        parser.expect(TokenKind::RFArrow)?;
+        (context, parser.parse_predicate()?)
+    } else {
+        let pred = parser.parse_predicate()?;
+        if parser.matches(TokenKind::RFArrow) { (vec![pred], parser.parse_predicate()?) } else { (vec![], pred) }
+    };
+    Ok((ExprKind::Instance { context, head }, None))
+}
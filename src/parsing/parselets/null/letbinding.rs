@@ -1,12 +1,17 @@
 use crate::parsing::{Parser, ExprKind, Precedence};
 use crate::typechecking::Ty;
-use regexlexer::{Token, TokenKind};
+use crate::lexing::{Token, TokenKind};
 use crate::error::Error;
 
 pub(crate) fn parse_let<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
     let binder = parser.parse_binder()?;
     parser.expect(TokenKind::Equal)?;
-    let bound = box parser.parse_expression(Precedence::ZERO)?;
+    let bound = Box::new(parser.parse_expression(Precedence::ZERO)?);
+    // Without `in` the binding is in scope for the rest of the enclosing block
+    if parser.matches(TokenKind::In) {
+        let body = Box::new(parser.parse_expression(Precedence::ZERO)?);
+        return Ok((ExprKind::LetIn { binder, bound, body }, None))
+    }
     let kind = ExprKind::Let { binder, bound };
     Ok((kind, None))
 }
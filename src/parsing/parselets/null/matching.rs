@@ -0,0 +1,13 @@
+use crate::parsing::{Parser, ExprKind, Precedence};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+/// Parses `match scrutinee { pat => expr, ... }`
+pub(crate) fn parse_match<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let scrutinee = Box::new(parser.parse_expression(Precedence::ZERO)?);
+    parser.expect(TokenKind::LBrace)?;
+    let (arms, _) = parser.parse_delimited(TokenKind::RBrace, Parser::parse_arm)?;
+    let kind = ExprKind::Match { scrutinee, arms };
+    Ok((kind, None))
+}
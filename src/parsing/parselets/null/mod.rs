@@ -1,5 +1,6 @@
 mod prefixop;
 mod integral;
+mod floating;
 mod id;
 mod group;
 mod boolean;
@@ -7,9 +8,16 @@ mod string;
 mod letbinding;
 mod block;
 mod lambda;
+mod conditional;
+mod typedef;
+mod matching;
+mod class;
+mod instance;
+mod record;
 
 pub(crate) use prefixop::parse_prefix_op;
 pub(crate) use integral::parse_integral;
+pub(crate) use floating::parse_floating;
 pub(crate) use id::parse_id;
 pub(crate) use group::parse_group;
 pub(crate) use boolean::parse_bool;
@@ -17,3 +25,9 @@ pub(crate) use string::parse_str;
 pub(crate) use letbinding::parse_let;
 pub(crate) use block::parse_block;
 pub(crate) use lambda::parse_lambda;
+pub(crate) use conditional::parse_if;
+pub(crate) use typedef::parse_typedef;
+pub(crate) use matching::parse_match;
+pub(crate) use class::parse_class;
+pub(crate) use instance::parse_instance;
+pub(crate) use record::{is_record, parse_record, parse_update};
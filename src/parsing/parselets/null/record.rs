@@ -0,0 +1,26 @@
+use crate::parsing::{Parser, Expr, ExprKind};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+/// Records share their opening brace with blocks and a record literal starts with a field `x =`
+/// Updates are only recognised by the `|` after their leading expression so they are parsed by the block
+/// `{}` is the empty block, there is no empty record literal
+pub(crate) fn is_record(parser: &mut Parser) -> bool {
+    let checkpoint = parser.checkpoint();
+    let is_record = parser.matches(TokenKind::Identifier) && parser.matches(TokenKind::Equal);
+    parser.backtrack(checkpoint);
+    is_record
+}
+
+/// Parses `{ x = expr, ... }` after the opening brace
+pub(crate) fn parse_record<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let (fields, _) = parser.parse_delimited(TokenKind::RBrace, Parser::parse_field)?;
+    Ok((ExprKind::Record { fields }, None))
+}
+
+/// Parses the fields of `{ record | x = expr, ... }` after the `|`
+pub(crate) fn parse_update(parser: &mut Parser, record: Expr) -> Result<(ExprKind, Option<Ty>), Error> {
+    let (fields, _) = parser.parse_delimited(TokenKind::RBrace, Parser::parse_field)?;
+    Ok((ExprKind::Update { record: Box::new(record), fields }, None))
+}
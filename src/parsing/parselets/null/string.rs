@@ -1,10 +1,11 @@
 use crate::parsing::{Parser, ExprKind};
-use regexlexer::Token;
+use crate::lexing::Token;
 use crate::error::Error;
-use crate::typechecking::Ty;
+use crate::typechecking::{Ty, TyKind};
 
 pub(crate) fn parse_str<'a>(parser: &mut Parser<'a>, token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
     // Trim surrounding quotes
     let string = token.lexeme[1..token.lexeme.len() - 1].to_owned();
-    Ok((ExprKind::Str { string }, None))
+    let ty = Ty::new(parser.get_single_span(), TyKind::Str);
+    Ok((ExprKind::Str { string }, Some(ty)))
 }
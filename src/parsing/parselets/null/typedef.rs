@@ -0,0 +1,17 @@
+use crate::parsing::{Parser, ExprKind};
+use crate::lexing::{Token, TokenKind};
+use crate::error::Error;
+use crate::typechecking::Ty;
+
+/// Parses algebraic data type declarations of the form `type Option<a> = None | Some(a)`
+pub(crate) fn parse_typedef<'a>(parser: &mut Parser<'a>, _token: Token<'a>) -> Result<(ExprKind, Option<Ty>), Error> {
+    let name = parser.expect(TokenKind::Typename)?.lexeme.to_owned();
+    let params = if parser.matches(TokenKind::LT) {
+        parser.parse_delimited(TokenKind::GT, |p| Ok(p.expect(TokenKind::Identifier)?.lexeme.to_owned()))?.0
+    } else { vec![] };
+    parser.expect(TokenKind::Equal)?;
+    let mut variants = vec![parser.parse_variant()?];
+    while parser.matches(TokenKind::Pipe) { variants.push(parser.parse_variant()?) }
+    let kind = ExprKind::TypeDef { name, params, variants };
+    Ok((kind, None))
+}
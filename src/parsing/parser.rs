@@ -1,8 +1,8 @@
-use super::{Expr, Precedence, Span, ExprKind, Binder};
-use regexlexer::{Token, TokenKind};
-use crate::error::Error;
+use super::{Expr, Precedence, Span, ExprKind, Binder, Variant, Arm, Field, Pattern, PatternKind};
+use crate::lexing::{Token, TokenKind};
+use crate::error::{Error, ErrorCode};
 use super::parselets::*;
-use crate::typechecking::{Ty, TyKind};
+use crate::typechecking::{Ty, TyKind, Predicate};
 use crate::util::Counter;
 
 pub struct Parser<'a> {
@@ -10,7 +10,7 @@ pub struct Parser<'a> {
     i: usize, // Current index inside tokens
     name_gen: &'a mut Counter,
     span_stack: Vec<usize>,
-    backtrack_index: usize,
+    errors: Vec<Error>, // Errors that have been recovered from
 }
 
 // Parser functions return a tuple of an exprkind as the fields of the expr can be filled out by the parser
@@ -20,29 +20,87 @@ type LeftParseFn = for<'r, 'b> fn(&'r mut Parser<'b>, Expr, Token<'b>) -> Result
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a Vec<Token<'a>>, name_gen: &'a mut Counter) -> Self {
-        Parser { tokens, i: 0, backtrack_index: 0, span_stack: Vec::new(), name_gen }
+        Parser { tokens, i: 0, span_stack: Vec::new(), name_gen, errors: Vec::new() }
     }
 
     /// Returns the index into the src file the parser is currently at
     pub(crate) fn src_index(&self) -> usize { self.curr_or_last().index }
-    pub(crate) fn src_line(&self) -> usize { self.curr_or_last().line }
 
-    fn get_span(&mut self) -> Span { Span::new(self.span_stack.pop().unwrap(), self.src_index(), self.src_line()) }
-    fn peek_span(&self) -> Span { Span::new(*self.span_stack.last().unwrap(), self.src_index(), self.src_line()) }
-    pub(crate) fn get_single_span(&self) -> Span { Span::single(self.src_index(), self.src_line()) }
+    fn get_span(&mut self) -> Span { Span::new(self.span_stack.pop().unwrap(), self.src_index()) }
+    fn peek_span(&self) -> Span { Span::new(*self.span_stack.last().unwrap(), self.src_index()) }
+    pub(crate) fn get_single_span(&self) -> Span { Span::single(self.src_index()) }
+    pub(crate) fn token_span(token: Token) -> Span { Span::new(token.index, token.index + token.lexeme.len()) }
+
+    /// The value of an integral token, which may be too large for an `Int`
+    pub(crate) fn integral_value(token: Token) -> Result<i64, Error> {
+        token.lexeme.parse::<i64>().map_err(|_| Error::new(Self::token_span(token), format!("Integer literal `{}` does not fit in an `Int`", token.lexeme))
+            .with_code(ErrorCode::IntegerOverflow))
+    }
+
+    /// The value of a floating token, which is rejected if it is too large to be finite as it could not be printed back as a literal
+    pub(crate) fn floating_value(token: Token) -> Result<f64, Error> {
+        match token.lexeme.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(Error::new(Self::token_span(token), format!("Float literal `{}` is too large for a `Float`", token.lexeme)).with_code(ErrorCode::FloatOverflow)),
+        }
+    }
 
     pub(crate) fn gen_id(&mut self) -> u64 { self.name_gen.next() }
 
     pub(crate) fn gen_type_var(&mut self) -> Ty {
-        Ty::new(Span::single(self.src_index(), self.src_line()), TyKind::Infer(self.gen_id()))
+        Ty::new(Span::single(self.src_index()), TyKind::Infer(self.gen_id()))
     }
 
     pub fn parse(&mut self) -> Result<Expr, Vec<Error>> {
-        let expr = self.parse_expression(Precedence::ZERO).map_err(|err| vec![err])?;
-        if self.peek().map(|x| x.kind) != Ok(TokenKind::EOF) {
-            return Err(vec![Error::new(expr.span, format!("Did not consume all tokens (debug::currently on {:?})", self.peek()))]);
+        match self.parse_expression(Precedence::ZERO) {
+            Ok(expr) => {
+                if self.peek().map(|x| x.kind) != Ok(TokenKind::EOF) {
+                    self.errors.push(Error::new(expr.span, format!("Did not consume all tokens (debug::currently on {:?})", self.peek())).with_code(ErrorCode::UnexpectedToken));
+                }
+                if self.errors.is_empty() { Ok(expr) } else { Err(std::mem::take(&mut self.errors)) }
+            }
+            Err(err) => {
+                self.errors.push(err);
+                Err(std::mem::take(&mut self.errors))
+            }
+        }
+    }
+
+    /// Parses a single statement of a block
+    /// On failure the error is recorded and the parser skips to the next statement boundary (`;` or `}`)
+    /// The leading statement may also be followed by `|` as it is the record of an update
+    pub(crate) fn parse_statement(&mut self, leading: bool) -> Option<Expr> {
+        let (start, spans) = (self.i, self.span_stack.len());
+        let result = self.parse_expression(Precedence::ZERO).and_then(|expr| match self.peek()?.kind {
+            TokenKind::SemiColon | TokenKind::RBrace => Ok(expr),
+            TokenKind::Pipe if leading => Ok(expr),
+            kind => Err(Error::new(self.get_single_span(), format!("Expected `;` or `}}` after statement, found `{}`", kind)).with_code(ErrorCode::UnexpectedToken)),
+        });
+        match result {
+            Ok(expr) => Some(expr),
+            Err(err) => {
+                self.errors.push(err);
+                self.span_stack.truncate(spans);
+                self.synchronize(start);
+                None
+            }
+        }
+    }
+
+    /// Skips forward from `start` to the next `;` or `}` that is not nested inside brackets
+    fn synchronize(&mut self, start: usize) {
+        let mut depth = 0;
+        self.i = start;
+        while let Ok(token) = self.peek() {
+            match token.kind {
+                TokenKind::EOF => return,
+                TokenKind::SemiColon | TokenKind::RBrace if depth == 0 => return,
+                TokenKind::LParen | TokenKind::LBrace => depth += 1,
+                TokenKind::RParen | TokenKind::RBrace => depth = if depth > 0 { depth - 1 } else { 0 },
+                _ => {}
+            }
+            self.i += 1;
         }
-        Ok(expr)
     }
 
     fn curr_precedence(&self) -> Precedence { self.peek().map(Precedence::of_left).unwrap_or(Precedence::ZERO) }
@@ -51,7 +109,7 @@ impl<'a> Parser<'a> {
         self.span_stack.push(self.src_index());
         let token = self.next()?;
         let null_parse_fn = Parser::get_null_denotation_rule(token.kind)
-            .ok_or(Error::new(self.peek_span(), format!("Failed to parse null denotation token `{}`", token)))?;
+            .ok_or(Error::new(self.peek_span(), format!("Failed to parse null denotation token `{}`", token)).with_code(ErrorCode::UnexpectedToken))?;
         let (kind, ty) = null_parse_fn(self, token)?;
         let mut expr = Expr::new(self.get_span(), kind, ty.unwrap_or(self.gen_type_var()), self.gen_id());
 
@@ -61,8 +119,10 @@ impl<'a> Parser<'a> {
             self.span_stack.push(self.src_index());
             let token = self.next()?;
             let left_parse_fn = Parser::get_left_denotation_rule(token.kind);
+            let left_span = expr.span;
             let (kind, ty) = left_parse_fn(self, expr, token)?;
-            expr = Expr::new(self.get_span(), kind, ty.unwrap_or(self.gen_type_var()), self.gen_id())
+            // The expression starts at its left operand rather than the operator
+            expr = Expr::new(left_span.merge(self.get_span()), kind, ty.unwrap_or(self.gen_type_var()), self.gen_id())
         }
         Ok(expr)
     }
@@ -73,37 +133,65 @@ impl<'a> Parser<'a> {
             Ok(Ty::new(self.get_span(), TyKind::Bool))
         } else if self.matches(TokenKind::Int) {
             Ok(Ty::new(self.get_span(), TyKind::I64))
+        } else if self.matches(TokenKind::Float) {
+            Ok(Ty::new(self.get_span(), TyKind::F64))
         } else if self.matches(TokenKind::LParen) {
-            self.set_backtrack();
+            let checkpoint = self.checkpoint();
             let ty = self.parse_type()?;
             // Parse single types within parens as a tuple
-            Ok(if self.matches(TokenKind::RParen) { ty } else {
-                self.backtrack();
-                let (types, span) = self.parse_tuple(Self::parse_type)?;
-                Ty::new(span, TyKind::Tuple(types))
+            Ok(if self.matches(TokenKind::RParen) {
+                self.span_stack.pop();
+                ty
+            } else {
+                self.backtrack(checkpoint);
+                let (types, _) = self.parse_tuple(Self::parse_type)?;
+                Ty::new(self.get_span(), TyKind::Tuple(types))
             })
+        } else if self.matches(TokenKind::LBrace) {
+            let (fields, _) = self.parse_delimited(TokenKind::RBrace, |parser| {
+                let name = parser.expect(TokenKind::Identifier)?.lexeme.to_owned();
+                parser.expect(TokenKind::Colon)?;
+                Ok((name, parser.parse_type()?))
+            })?;
+            Ok(Ty::new(self.get_span(), TyKind::record(fields, None)))
         } else if self.matches(TokenKind::Fn) {
             self.expect(TokenKind::LParen)?;
             let (l, span) = self.parse_tuple(Self::parse_type)?;
-            let ttuple = box Ty::new(span, TyKind::Tuple(l));
+            let ttuple = Box::new(Ty::new(span, TyKind::Tuple(l)));
             self.expect(TokenKind::RArrow)?;
-            let r = box self.parse_type()?;
+            let r = Box::new(self.parse_type()?);
             let kind = TyKind::Arrow(ttuple, r);
             Ok(Ty::new(self.get_span(), kind))
-        } else if self.matches(TokenKind::Typename) {
-            unimplemented!();
+        } else if self.peek().map(|t| t.kind) == Ok(TokenKind::Typename) {
+            let token = self.next()?;
+            match token.lexeme {
+                "String" => Ok(Ty::new(self.get_span(), TyKind::Str)),
+                name     => {
+                    let args = if self.matches(TokenKind::LT) { self.parse_delimited(TokenKind::GT, Self::parse_type)?.0 } else { vec![] };
+                    Ok(Ty::new(self.get_span(), TyKind::Adt(name.to_owned(), args)))
+                }
+            }
+        } else if self.peek().map(|t| t.kind) == Ok(TokenKind::Identifier) {
+            let name = self.next()?.lexeme.to_owned();
+            Ok(Ty::new(self.get_span(), TyKind::TyVar(name)))
         } else {
-            unimplemented!();
+            let token = self.next()?;
+            Err(Error::new(self.get_span(), format!("Expected type, found `{}`", token.kind)).with_code(ErrorCode::UnexpectedToken))
         }
     }
 
     pub(crate) fn parse_tuple<T>(&mut self, parse_fn: impl Fn(&mut Parser<'a>) -> Result<T, Error>) -> Result<(Vec<T>, Span), Error> {
+        self.parse_delimited(TokenKind::RParen, parse_fn)
+    }
+
+    /// Parses a comma separated list up to and including the closing token
+    pub(crate) fn parse_delimited<T>(&mut self, close: TokenKind, parse_fn: impl Fn(&mut Parser<'a>) -> Result<T, Error>) -> Result<(Vec<T>, Span), Error> {
         self.span_stack.push(self.src_index());
         let mut vec = vec![];
-        while !self.matches(TokenKind::RParen) {
+        while !self.matches(close) {
             vec.push(parse_fn(self)?);
             if !self.matches(TokenKind::Comma) {
-                self.expect(TokenKind::RParen)?;
+                self.expect(close)?;
                 break;
             }
         }
@@ -118,16 +206,83 @@ impl<'a> Parser<'a> {
         Ok(Binder::new(self.get_span(), name, ty))
     }
 
+    pub(crate) fn parse_arm(&mut self) -> Result<Arm, Error> {
+        self.span_stack.push(self.src_index());
+        let pat = self.parse_pattern()?;
+        self.expect(TokenKind::RFArrow)?;
+        let body = self.parse_expression(Precedence::ZERO)?;
+        Ok(Arm::new(self.get_span(), pat, body))
+    }
+
+    pub(crate) fn parse_field(&mut self) -> Result<Field, Error> {
+        self.span_stack.push(self.src_index());
+        let name = self.expect(TokenKind::Identifier)?.lexeme.to_owned();
+        self.expect(TokenKind::Equal)?;
+        let value = self.parse_expression(Precedence::ZERO)?;
+        Ok(Field::new(self.get_span(), name, value))
+    }
+
+    pub(crate) fn parse_pattern(&mut self) -> Result<Pattern, Error> {
+        self.span_stack.push(self.src_index());
+        let token = self.next()?;
+        let kind = match token.kind {
+            TokenKind::Underscore => PatternKind::Wildcard,
+            TokenKind::Identifier => PatternKind::Binding { name: token.lexeme.to_owned() },
+            TokenKind::Integral   => PatternKind::Integral { value: Self::integral_value(token)? },
+            TokenKind::False | TokenKind::True => PatternKind::Bool { b: token.lexeme.parse::<bool>().unwrap() },
+            TokenKind::Str        => PatternKind::Str { string: token.lexeme[1..token.lexeme.len() - 1].to_owned() },
+            TokenKind::Typename   => {
+                let args = if self.matches(TokenKind::LParen) { self.parse_tuple(Self::parse_pattern)?.0 } else { vec![] };
+                PatternKind::Constructor { name: token.lexeme.to_owned(), args }
+            }
+            TokenKind::LParen if self.matches(TokenKind::RParen) => PatternKind::Tuple { elems: vec![] },
+            TokenKind::LParen => {
+                let first = self.parse_pattern()?;
+                // A single pattern within parens is just a grouping
+                if self.matches(TokenKind::RParen) {
+                    self.span_stack.pop();
+                    return Ok(first)
+                }
+                self.expect(TokenKind::Comma)?;
+                let (rest, _) = self.parse_tuple(Self::parse_pattern)?;
+                PatternKind::Tuple { elems: std::iter::once(first).chain(rest).collect() }
+            }
+            kind => return Err(Error::new(self.peek_span(), format!("Expected pattern, found `{}`", kind)).with_code(ErrorCode::UnexpectedToken)),
+        };
+        Ok(Pattern::new(self.get_span(), kind))
+    }
+
+    /// Parses a class name applied to a type such as `Eq Option<a>`
+    pub(crate) fn parse_predicate(&mut self) -> Result<Predicate, Error> {
+        let class = self.expect(TokenKind::Typename)?.lexeme.to_owned();
+        let ty = self.parse_type()?;
+        Ok(Predicate::new(class, ty))
+    }
+
+    pub(crate) fn parse_variant(&mut self) -> Result<Variant, Error> {
+        self.span_stack.push(self.src_index());
+        let name = self.expect(TokenKind::Typename)?.lexeme.to_owned();
+        let fields = if self.matches(TokenKind::LParen) { self.parse_tuple(Self::parse_type)?.0 } else { vec![] };
+        Ok(Variant::new(self.get_span(), name, fields))
+    }
+
     /// Returns the relevant null denotation parse function for the tokenkind
     fn get_null_denotation_rule(token_kind: TokenKind) -> Option<NullParseFn> {
         match token_kind {
             TokenKind::Integral   => Some(parse_integral),
+            TokenKind::Floating   => Some(parse_floating),
             TokenKind::LParen     => Some(parse_group),
             TokenKind::Identifier => Some(parse_id),
             TokenKind::Str        => Some(parse_str),
             TokenKind::Let        => Some(parse_let),
             TokenKind::LBrace     => Some(parse_block),
             TokenKind::Fn         => Some(parse_lambda),
+            TokenKind::If         => Some(parse_if),
+            TokenKind::Type       => Some(parse_typedef),
+            TokenKind::Class      => Some(parse_class),
+            TokenKind::Instance   => Some(parse_instance),
+            TokenKind::Match      => Some(parse_match),
+            TokenKind::Typename   => Some(parse_id), // Constructors are just functions
             TokenKind::False | TokenKind::True => Some(parse_bool),
             TokenKind::Plus | TokenKind::Minus | TokenKind::Tilde | TokenKind::Bang => Some(parse_prefix_op),
             _ => None
@@ -137,7 +292,10 @@ impl<'a> Parser<'a> {
     fn get_left_denotation_rule(token_kind: TokenKind) -> LeftParseFn {
         match token_kind {
             TokenKind::LParen => parse_application,
+            TokenKind::As     => parse_cast,
+            TokenKind::Dot    => parse_access,
             TokenKind::Plus
+                | TokenKind::DPlus
                 | TokenKind::Minus
                 | TokenKind::Slash
                 | TokenKind::Star
@@ -154,18 +312,20 @@ impl<'a> Parser<'a> {
 
     /// Returns ref to current token and pushes the index forward if the peek is succesful
     fn next(&mut self) -> Result<Token<'a>, Error> {
-        self.peek().map(|tok| { self.i += 1; tok })
+        self.peek().inspect(|_| self.i += 1)
     }
 
-    pub(crate) fn set_backtrack(&mut self) { self.backtrack_index = self.i }
-    pub(crate) fn backtrack(&mut self) { self.i = self.backtrack_index }
+    /// Returns a position that can be backtracked to
+    /// Checkpoints are values rather than a single slot so nested constructs such as `((1, 2), 3)` can each backtrack
+    pub(crate) fn checkpoint(&self) -> usize { self.i }
+    pub(crate) fn backtrack(&mut self, checkpoint: usize) { self.i = checkpoint }
 
     /// Returns ref to current token or an error if the current token is at EOF or even further
     fn peek(&self) -> Result<Token<'a>, Error> {
         if self.i < self.tokens.len() {
             Ok(self.tokens[self.i])
         } else {
-            Err(Error::new(Span::single(self.src_index(), self.src_line()), "Ran out of tokens".to_owned()))
+            Err(Error::new(Span::single(self.src_index()), "Ran out of tokens".to_owned()).with_code(ErrorCode::UnexpectedEof))
         }
     }
 
@@ -176,7 +336,7 @@ impl<'a> Parser<'a> {
             self.i += 1;
             Ok(curr)
         } else {
-            Err(Error::new(Span::single(self.src_index(), self.src_line()), format!("Expected `{}` found `{}`", kind, curr.kind)))
+            Err(Error::new(Span::single(self.src_index()), format!("Expected `{}` found `{}`", kind, curr.kind)).with_code(ErrorCode::UnexpectedToken))
         }
     }
 
@@ -189,7 +349,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Convenience method for grabbing a token for error handling purposes
-    fn curr_or_last(&self) -> Token {
+    fn curr_or_last(&self) -> Token<'a> {
         if self.i < self.tokens.len() { self.tokens[self.i] }
         else { *self.tokens.last().unwrap() }
     }
@@ -215,3 +375,47 @@ fn gen<'a>() -> Vec<Box<dyn Fn(&mut Parser, Token) -> Result<Expr, Error>>> {
 
 
 
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn recovers_at_statement_boundaries() {
+        let errors = crate::generate_ast("{ let x = ; let y = 5 +; (1 2); 3 }").unwrap_err();
+        assert_eq!(errors.len(), 3)
+    }
+
+    #[test]
+    fn recovers_in_nested_blocks() {
+        let errors = crate::generate_ast("{ { let x = ; 1 }; let = 2; 3 }").unwrap_err();
+        assert_eq!(errors.len(), 2)
+    }
+
+    #[test]
+    fn overflowing_integer_pattern_fails() {
+        let errors = crate::generate_ast("match 1 { 99999999999999999999 => 1, _ => 0 }").unwrap_err();
+        assert_eq!((errors[0].code, errors[0].span.lo), (Some(crate::ErrorCode::IntegerOverflow), 10))
+    }
+
+    #[test]
+    fn overflowing_float_literal_fails() {
+        let errors = crate::generate_ast("1.5 * 1e400").unwrap_err();
+        assert_eq!((errors[0].code, errors[0].span.lo), (Some(crate::ErrorCode::FloatOverflow), 6))
+    }
+
+    #[test]
+    fn parses_updates_of_any_record_expression() {
+        let ast = crate::generate_ast("{ let r = { inner = { x = 1 } }; { r.inner | x = 2 } }").unwrap().1;
+        assert!(format!("{:?}", ast).contains("(update "));
+        let ast = crate::generate_ast("{ let f = fn r => r; { f({ x = 1 }) | x = 2 } }").unwrap().1;
+        assert!(format!("{:?}", ast).contains("(update "));
+        // Pipes in a type definition do not make the block a record
+        assert!(crate::generate_ast("{ type T = A | B; A }").is_ok());
+        assert!(crate::generate_ast("{ let r = { x = 1 }; 1; r | x = 2 }").is_err());
+    }
+
+    #[test]
+    fn overflowing_integer_literal_fails() {
+        let errors = crate::generate_ast("1 + 9223372036854775808").unwrap_err();
+        assert_eq!((errors[0].code, errors[0].span.lo), (Some(crate::ErrorCode::IntegerOverflow), 4))
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt::{self, Display, Formatter, Debug};
+use crate::parsing::{Span, fmt_vec};
+
+#[derive(Clone, PartialEq)]
+pub struct Pattern {
+    pub span: Span,
+    pub kind: PatternKind,
+}
+
+impl Pattern {
+    pub fn new(span: Span, kind: PatternKind) -> Self {
+        Self { span, kind }
+    }
+
+    /// The variables bound by the pattern along with their spans
+    pub fn bindings(&self) -> Vec<(&str, Span)> {
+        match &self.kind {
+            PatternKind::Binding { name } => vec![(name.as_str(), self.span)],
+            PatternKind::Tuple { elems: pats } | PatternKind::Constructor { args: pats, .. } => pats.iter().flat_map(Pattern::bindings).collect(),
+            PatternKind::Wildcard | PatternKind::Integral { .. } | PatternKind::Bool { .. } | PatternKind::Str { .. } => vec![],
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{}", self.kind) }
+}
+
+impl Debug for Pattern {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{}", self.kind) }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum PatternKind {
+    Wildcard,
+    Binding { name: String },
+    Integral { value: i64 },
+    Bool { b: bool },
+    Str { string: String },
+    Tuple { elems: Vec<Pattern> },
+    Constructor { name: String, args: Vec<Pattern> },
+}
+
+impl Display for PatternKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Wildcard                  => write!(f, "_"),
+            Self::Binding { name }          => write!(f, "{}", name),
+            Self::Integral { value }        => write!(f, "{}", value),
+            Self::Bool { b }                => write!(f, "{}", b),
+            Self::Str { string }            => write!(f, "\"{}\"", string),
+            Self::Tuple { elems }           => write!(f, "({})", fmt_vec(elems, ", ")),
+            Self::Constructor { name, args } if args.is_empty() => write!(f, "{}", name),
+            Self::Constructor { name, args } => write!(f, "{}({})", name, fmt_vec(args, ", ")),
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::ops::{Sub};
-use regexlexer::Token;
-use regexlexer::TokenKind::*;
+use crate::lexing::{Token, TokenKind};
+use crate::lexing::TokenKind::*;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Debug)]
 pub enum Precedence {
     ZERO    = 0,
@@ -27,17 +28,27 @@ pub enum Precedence {
 impl Precedence {
     /// Precedence of left denotation parselets
     pub fn of_left(token: Token) -> Self {
-        match token.kind {
-            Plus | Minus        => Self::TERM,
+        Self::of_op(token.kind)
+    }
+
+    pub fn of_op(kind: TokenKind) -> Self {
+        match kind {
+            Plus | DPlus | Minus => Self::TERM,
             Star | Slash        => Self::FACTOR,
             DStar               => Self::EXPO,
+            As                  => Self::CAST,
             DEqual | BangEqual  => Self::EQ,
             GT | GTE | LT | LTE => Self::CMP,
             EOF                 => Self::ZERO,
-            LParen              => Self::CALL,
+            LParen | Dot        => Self::CALL,
             _                   => Self::ZERO,
         }
     }
+
+    /// Assignment and exponentiation are right associative
+    pub fn right_associative(kind: TokenKind) -> bool {
+        matches!(kind, DStar | Equal)
+    }
 }
 
 
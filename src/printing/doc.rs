@@ -0,0 +1,118 @@
+/// A document in the style of Wadler's "A prettier printer"
+/// Each group is laid out on a single line if it fits, otherwise its lines are broken
+#[derive(Clone, Debug)]
+pub(crate) enum Doc {
+    Text(String),
+    /// A space when flat, otherwise a newline
+    Line,
+    /// Nothing when flat, otherwise a newline
+    SoftLine,
+    /// Always a newline, so the enclosing groups are never flat
+    HardLine,
+    /// Text deferred to the end of the line, which breaks the enclosing groups so that the line ends soon after
+    LineSuffix(String),
+    Concat(Vec<Doc>),
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+type Cmd<'d> = (usize, Mode, &'d Doc);
+
+impl Doc {
+    pub fn text(s: impl Into<String>) -> Self {
+        Doc::Text(s.into())
+    }
+
+    pub fn concat(docs: Vec<Doc>) -> Self {
+        Doc::Concat(docs)
+    }
+
+    pub fn nest(self, indent: usize) -> Self {
+        Doc::Nest(indent, Box::new(self))
+    }
+
+    pub fn group(self) -> Self {
+        Doc::Group(Box::new(self))
+    }
+
+    pub fn join(docs: Vec<Doc>, sep: Doc) -> Self {
+        let mut joined = Vec::with_capacity(2 * docs.len());
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 { joined.push(sep.clone()) }
+            joined.push(doc);
+        }
+        Doc::Concat(joined)
+    }
+
+    /// Lays out the document keeping lines within `width` where possible
+    pub fn render(&self, width: usize) -> String {
+        let mut out = String::new();
+        let mut col = 0;
+        let mut suffix = String::new();
+        let mut stack: Vec<Cmd> = vec![(0, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(s) => {
+                    out.push_str(s);
+                    col += s.chars().count();
+                }
+                Doc::LineSuffix(s) => suffix.push_str(s),
+                Doc::Line | Doc::SoftLine if mode == Mode::Flat => if let Doc::Line = doc {
+                    out.push(' ');
+                    col += 1;
+                }
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    out.push_str(&suffix);
+                    suffix.clear();
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    col = indent;
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+                Doc::Nest(i, doc) => stack.push((indent + i, mode, doc)),
+                Doc::Group(doc) => {
+                    let flat = mode == Mode::Flat || fits(width.saturating_sub(col), (indent, Mode::Flat, doc), &stack);
+                    stack.push((indent, if flat { Mode::Flat } else { Mode::Break }, doc));
+                }
+            }
+        }
+        out.push_str(&suffix);
+        out
+    }
+}
+
+/// Whether `next` laid out flat, followed by the rest of the line, fits in the remaining width
+fn fits(mut remaining: usize, next: Cmd, rest: &[Cmd]) -> bool {
+    let mut stack = vec![next];
+    let mut rest = rest.iter().rev();
+    loop {
+        let (indent, mode, doc) = match stack.pop().or_else(|| rest.next().copied()) {
+            Some(cmd) => cmd,
+            None => return true,
+        };
+        match doc {
+            Doc::Text(s) => {
+                let len = s.chars().count();
+                if len > remaining { return false }
+                remaining -= len;
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine if mode == Mode::Break => return true,
+            Doc::HardLine => return false,
+            Doc::LineSuffix(_) => if mode == Mode::Flat { return false },
+            Doc::Line => {
+                if remaining == 0 { return false }
+                remaining -= 1;
+            }
+            Doc::SoftLine => {}
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+            Doc::Nest(i, doc) => stack.push((indent + i, mode, doc)),
+            Doc::Group(doc) => stack.push((indent, mode, doc)),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod doc;
+mod printer;
+
+pub use printer::Printer;
+pub(crate) use doc::Doc;
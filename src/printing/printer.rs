@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use crate::parsing::{Expr, ExprKind, Binder, Variant, Arm, Field, Precedence};
+use crate::typechecking::{Ty, TyKind, Predicate};
+use crate::cst::{SyntaxNode, SyntaxElement, Trivia, TriviaKind};
+use crate::error::Error;
+use super::Doc;
+
+const INDENT: usize = 4;
+
+/// Prints expressions back into source in a canonical layout
+/// Parentheses are only emitted where precedence or associativity requires them
+pub struct Printer {
+    width: usize,
+}
+
+impl Printer {
+    pub fn new(width: usize) -> Self {
+        Self { width }
+    }
+
+    pub fn print(&self, expr: &Expr) -> String {
+        doc(&Comments::default(), expr).render(self.width)
+    }
+
+    pub(crate) fn print_kind(&self, kind: &ExprKind) -> String {
+        kind_doc(&Comments::default(), kind).render(self.width)
+    }
+
+    /// Prints the expression of a concrete syntax tree keeping its comments
+    pub fn print_cst(&self, root: &SyntaxNode) -> Result<String, Vec<Error>> {
+        let expr = root.to_expr()?;
+        let mut comments = Comments::default();
+        let (_, trailing) = comments.collect(root, vec![&expr]);
+        comments.0.entry(&expr as *const Expr).or_default().1.extend(trailing);
+        Ok(doc(&comments, &expr).render(self.width))
+    }
+}
+
+/// The comments printed before and after each expression, keyed by the address of the expression
+#[derive(Default)]
+struct Comments(HashMap<*const Expr, (Vec<Doc>, Vec<Doc>)>);
+
+impl Comments {
+    /// Attaches the comments among the node's own tokens to the expressions of its child nodes
+    /// Comments ending a line follow the expression before them and comments on their own line lead the expression after them
+    /// Returns the comments leading the node's first token and those that have no expression after them
+    fn collect(&mut self, node: &SyntaxNode, exprs: Vec<&Expr>) -> (Vec<Doc>, Vec<Doc>) {
+        let mut exprs = exprs.into_iter();
+        let mut leading = vec![];
+        // Comments waiting for the next expression to lead them
+        let mut pending: Vec<&Trivia> = vec![];
+        let mut prev: Option<*const Expr> = None;
+        for (i, child) in node.children.iter().enumerate() {
+            match child {
+                SyntaxElement::Token(token) => {
+                    if i == 0 { leading = Self::leading(token.leading.iter()) } else { pending.extend(&token.leading) }
+                    // Comments after the closing token follow the whole node
+                    match prev {
+                        Some(prev) if i + 1 < node.children.len() => self.0.entry(prev).or_default().1.extend(token.trailing.iter().filter_map(Self::trailing)),
+                        _ => pending.extend(&token.trailing),
+                    }
+                }
+                SyntaxElement::Node(node) => {
+                    let expr = exprs.next().expect("a child expression for each child node");
+                    let (before, after) = self.collect(node, expr.children());
+                    let comments = self.0.entry(expr as *const Expr).or_default();
+                    comments.0.extend(Self::leading(pending.drain(..)).into_iter().chain(before));
+                    comments.1.extend(after);
+                    prev = Some(expr);
+                }
+            }
+        }
+        (leading, pending.into_iter().filter_map(Self::trailing).collect())
+    }
+
+    /// Comments that were followed by a newline stay on their own line
+    fn leading<'t>(trivia: impl Iterator<Item = &'t Trivia>) -> Vec<Doc> {
+        let trivia = trivia.filter(|trivia| trivia.kind != TriviaKind::Whitespace).collect::<Vec<_>>();
+        trivia.iter().enumerate().filter(|(_, trivia)| trivia.kind != TriviaKind::Newline).map(|(i, comment)| {
+            let own_line = comment.kind == TriviaKind::LineComment || trivia.get(i + 1).is_some_and(|next| next.kind == TriviaKind::Newline);
+            Doc::concat(vec![Doc::text(comment.text.as_str()), if own_line { Doc::HardLine } else { Doc::text(" ") }])
+        }).collect()
+    }
+
+    fn trailing(trivia: &Trivia) -> Option<Doc> {
+        match trivia.kind {
+            TriviaKind::LineComment  => Some(Doc::LineSuffix(format!(" {}", trivia.text))),
+            TriviaKind::BlockComment => Some(Doc::text(format!(" {}", trivia.text))),
+            TriviaKind::Whitespace | TriviaKind::Newline => None,
+        }
+    }
+}
+
+/// The expression surrounded by its comments
+fn doc(c: &Comments, expr: &Expr) -> Doc {
+    let doc = kind_doc(c, &expr.kind);
+    match c.0.get(&(expr as *const Expr)) {
+        Some((leading, trailing)) => Doc::concat(leading.iter().cloned().chain(std::iter::once(doc)).chain(trailing.iter().cloned()).collect()),
+        None => doc,
+    }
+}
+
+fn kind_doc(c: &Comments, kind: &ExprKind) -> Doc {
+    match kind {
+        ExprKind::Integral { value } => Doc::text(value.to_string()),
+        // Debug formatting always keeps a fraction or exponent so the literal is not reparsed as an integer
+        ExprKind::Floating { value } => Doc::text(format!("{:?}", value)),
+        ExprKind::Bool { b }         => Doc::text(b.to_string()),
+        ExprKind::Id { name }        => Doc::text(name.as_str()),
+        ExprKind::Str { string }     => Doc::text(format!("\"{}\"", string)),
+        ExprKind::Grouping { expr }  => doc(c, expr),
+        ExprKind::Unary { op, expr, .. } => Doc::concat(vec![Doc::text(op.to_string()), operand(c, expr, precedence(expr) < Precedence::UNARY)]),
+        ExprKind::Cast { expr, ty } => Doc::concat(vec![operand(c, expr, precedence(expr) < Precedence::CAST), Doc::text(format!(" as {}", ty_str(ty)))]),
+        ExprKind::Binary { op, left, right, .. } => {
+            let prec = Precedence::of_op(*op);
+            let right_assoc = Precedence::right_associative(*op);
+            let (lp, rp) = (precedence(left), precedence(right));
+            Doc::concat(vec![
+                operand(c, left, lp < prec || lp == prec && right_assoc),
+                Doc::text(format!(" {}", op)),
+                Doc::concat(vec![Doc::Line, operand(c, right, rp < prec || rp == prec && !right_assoc)]).nest(INDENT),
+            ]).group()
+        }
+        ExprKind::Let { binder, bound } => Doc::concat(vec![
+            Doc::text(format!("let {} =", binder_str(binder))),
+            Doc::concat(vec![Doc::Line, doc(c, bound)]).nest(INDENT),
+        ]).group(),
+        ExprKind::LetIn { binder, bound, body } => Doc::concat(vec![
+            Doc::text(format!("let {} =", binder_str(binder))),
+            Doc::concat(vec![Doc::Line, doc(c, bound)]).nest(INDENT),
+            Doc::Line,
+            Doc::text("in "),
+            doc(c, body),
+        ]).group(),
+        ExprKind::Block { exprs, .. } if exprs.is_empty() => Doc::text("{}"),
+        ExprKind::Block { exprs, suppressed } => Doc::concat(vec![
+            Doc::text("{"),
+            Doc::concat(vec![
+                Doc::Line,
+                Doc::join(exprs.iter().map(|expr| doc(c, expr)).collect(), Doc::concat(vec![Doc::text(";"), Doc::Line])),
+                Doc::text(if *suppressed { ";" } else { "" }),
+            ]).nest(INDENT),
+            Doc::Line,
+            Doc::text("}"),
+        ]).group(),
+        ExprKind::Lambda { params, ret, body } => {
+            let params = match params.as_slice() {
+                [param] => binder_str(param),
+                params  => format!("({})", params.iter().map(binder_str).collect::<Vec<_>>().join(", ")),
+            };
+            // The return type annotation follows the body so an open ended body must be parenthesised
+            let body = if ret.is_some() { operand(c, body, precedence(body) == Precedence::ZERO) } else { doc(c, body) };
+            Doc::concat(vec![
+                Doc::text(format!("fn {} =>", params)),
+                Doc::concat(vec![Doc::Line, body]).nest(INDENT),
+                Doc::text(ret.as_ref().map_or(String::new(), |ret| format!(" -> {}", ty_str(ret)))),
+            ]).group()
+        }
+        ExprKind::App { f, args } => Doc::concat(vec![
+            operand(c, f, precedence(f) < Precedence::CALL),
+            delimited(args.iter().map(|arg| doc(c, arg)).collect(), ""),
+        ]),
+        ExprKind::Tuple { elems } => delimited(elems.iter().map(|elem| doc(c, elem)).collect(), if elems.len() == 1 { "," } else { "" }),
+        ExprKind::Record { fields } => braced(c, Doc::text("{"), fields),
+        ExprKind::Update { record, fields } => braced(c, Doc::concat(vec![Doc::text("{ "), doc(c, record), Doc::text(" |")]), fields),
+        ExprKind::Access { expr, field, .. } => Doc::concat(vec![operand(c, expr, precedence(expr) < Precedence::CALL), Doc::text(format!(".{}", field))]),
+        ExprKind::If { cond, then, otherwise } => {
+            // Without parentheses an else would attach to a trailing if in the then branch
+            let then = operand(c, then, otherwise.is_some() && dangling(then));
+            let mut branches = vec![Doc::Line, Doc::text("then "), then];
+            if let Some(otherwise) = otherwise {
+                branches.extend(vec![Doc::Line, Doc::text("else "), doc(c, otherwise)]);
+            }
+            Doc::concat(vec![Doc::text("if "), doc(c, cond), Doc::concat(branches).nest(INDENT)]).group()
+        }
+        ExprKind::TypeDef { name, params, variants } => {
+            let params = if params.is_empty() { String::new() } else { format!("<{}>", params.join(", ")) };
+            Doc::concat(vec![
+                Doc::text(format!("type {}{} =", name, params)),
+                Doc::concat(vec![
+                    Doc::Line,
+                    Doc::join(variants.iter().map(|v| Doc::text(variant_str(v))).collect(), Doc::concat(vec![Doc::Line, Doc::text("| ")])),
+                ]).nest(INDENT),
+            ]).group()
+        }
+        ExprKind::Class { name, param } => Doc::text(format!("class {} {}", name, param)),
+        ExprKind::Instance { context, head } => {
+            let context = match context.as_slice() {
+                [] => String::new(),
+                [pred] => format!("{} => ", predicate_str(pred)),
+                preds => format!("({}) => ", preds.iter().map(predicate_str).collect::<Vec<_>>().join(", ")),
+            };
+            Doc::text(format!("instance {}{}", context, predicate_str(head)))
+        }
+        ExprKind::Match { scrutinee, arms } if arms.is_empty() => Doc::concat(vec![Doc::text("match "), doc(c, scrutinee), Doc::text(" {}")]),
+        ExprKind::Match { scrutinee, arms } => Doc::concat(vec![
+            Doc::text("match "),
+            doc(c, scrutinee),
+            Doc::text(" {"),
+            Doc::concat(vec![
+                Doc::Line,
+                Doc::join(arms.iter().map(|a| arm(c, a)).collect(), Doc::concat(vec![Doc::text(","), Doc::Line])),
+            ]).nest(INDENT),
+            Doc::Line,
+            Doc::text("}"),
+        ]).group(),
+    }
+}
+
+fn arm(c: &Comments, arm: &Arm) -> Doc {
+    Doc::concat(vec![
+        Doc::text(format!("{} =>", arm.pat)),
+        Doc::concat(vec![Doc::Line, doc(c, &arm.body)]).nest(INDENT),
+    ]).group()
+}
+
+/// Comma separated docs within parentheses, broken one per line if they do not fit
+fn delimited(docs: Vec<Doc>, trailing: &str) -> Doc {
+    Doc::concat(vec![
+        Doc::text("("),
+        Doc::concat(vec![Doc::SoftLine, Doc::join(docs, Doc::concat(vec![Doc::text(","), Doc::Line])), Doc::text(trailing)]).nest(INDENT),
+        Doc::SoftLine,
+        Doc::text(")"),
+    ]).group()
+}
+
+/// Comma separated fields up to the closing brace, broken one per line if they do not fit
+fn braced(c: &Comments, open: Doc, fields: &[Field]) -> Doc {
+    let fields = fields.iter().map(|field| Doc::concat(vec![Doc::text(format!("{} = ", field.name)), doc(c, &field.value)])).collect();
+    Doc::concat(vec![
+        open,
+        Doc::concat(vec![Doc::Line, Doc::join(fields, Doc::concat(vec![Doc::text(","), Doc::Line]))]).nest(INDENT),
+        Doc::Line,
+        Doc::text("}"),
+    ]).group()
+}
+
+fn operand(c: &Comments, expr: &Expr, parens: bool) -> Doc {
+    if parens { Doc::concat(vec![Doc::text("("), doc(c, expr), Doc::text(")")]) } else { doc(c, expr) }
+}
+
+/// The precedence of an expression as an operand
+/// Expressions extending as far right as possible bind loosest of all
+fn precedence(expr: &Expr) -> Precedence {
+    match &expr.kind {
+        ExprKind::Grouping { expr } => precedence(expr),
+        ExprKind::Binary { op, .. } => Precedence::of_op(*op),
+        ExprKind::Unary { .. }      => Precedence::UNARY,
+        ExprKind::Cast { .. }       => Precedence::CAST,
+        ExprKind::App { .. } | ExprKind::Access { .. } => Precedence::CALL,
+        ExprKind::Let { .. } | ExprKind::LetIn { .. } | ExprKind::Lambda { .. } | ExprKind::If { .. } | ExprKind::TypeDef { .. }
+            | ExprKind::Class { .. } | ExprKind::Instance { .. } => Precedence::ZERO,
+        _                           => Precedence::PRIMARY,
+    }
+}
+
+/// Whether the printed expression ends in an if without an else
+fn dangling(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::If { otherwise: None, .. } => true,
+        ExprKind::If { otherwise: Some(expr), .. } | ExprKind::Let { bound: expr, .. } | ExprKind::LetIn { body: expr, .. }
+            | ExprKind::Grouping { expr } => dangling(expr),
+        ExprKind::Lambda { ret, body, .. } => ret.is_none() && dangling(body),
+        _ => false,
+    }
+}
+
+/// Unannotated binders are given fresh inference variables by the parser
+fn binder_str(binder: &Binder) -> String {
+    match binder.ty.kind {
+        TyKind::Infer(_) => binder.name.clone(),
+        _ => format!("{}: {}", binder.name, ty_str(&binder.ty)),
+    }
+}
+
+fn predicate_str(pred: &Predicate) -> String {
+    format!("{} {}", pred.class, ty_str(&pred.ty))
+}
+
+fn variant_str(variant: &Variant) -> String {
+    if variant.fields.is_empty() { variant.name.clone() }
+    else { format!("{}({})", variant.name, variant.fields.iter().map(ty_str).collect::<Vec<_>>().join(", ")) }
+}
+
+/// Types in the syntax accepted by the parser
+fn ty_str(ty: &Ty) -> String {
+    let join = |tys: &[Ty]| tys.iter().map(ty_str).collect::<Vec<_>>().join(", ");
+    match &ty.kind {
+        TyKind::Bool => "Bool".to_owned(),
+        TyKind::I64  => "Int".to_owned(),
+        TyKind::F64  => "Float".to_owned(),
+        TyKind::Str  => "String".to_owned(),
+        TyKind::Tuple(tys) if tys.len() == 1 => format!("({},)", ty_str(&tys[0])),
+        TyKind::Tuple(tys) => format!("({})", join(tys)),
+        TyKind::Record(fields, None) => format!("{{ {} }}", fields.iter().map(|(name, t)| format!("{}: {}", name, ty_str(t))).collect::<Vec<_>>().join(", ")),
+        TyKind::Record(..) => ty.to_string(),
+        TyKind::Arrow(box Ty { kind: TyKind::Tuple(params), .. }, ret) => format!("fn ({}) -> {}", join(params), ty_str(ret)),
+        TyKind::Arrow(param, ret) => format!("fn ({}) -> {}", ty_str(param), ty_str(ret)),
+        TyKind::Adt(name, args) if args.is_empty() => name.clone(),
+        TyKind::Adt(name, args) => format!("{}<{}>", name, join(args)),
+        TyKind::TyVar(_) | TyKind::Infer(_) | TyKind::Erased => ty.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::Parser;
+    use crate::util::Counter;
+    use regex::Regex;
+    use crate::lexing::Lexer;
+
+    fn parse(src: &str) -> Expr {
+        let tokens = Lexer::new(src).lex().unwrap();
+        let mut gen = Counter::new();
+        Parser::new(&tokens, &mut gen).parse().unwrap()
+    }
+
+    fn fmt(src: &str, width: usize) -> String {
+        Printer::new(width).print(&parse(src))
+    }
+
+    /// The formatted source parses to the same tree and formatting it again changes nothing
+    fn roundtrip(src: &str) -> String {
+        let vars = Regex::new(r"τ\d+").unwrap();
+        let tree = |src| vars.replace_all(&format!("{:?}", parse(src)), "τ").into_owned();
+        let formatted = fmt(src, 80);
+        assert_eq!(tree(src), tree(&formatted), "`{}` formatted as `{}`", src, formatted);
+        assert_eq!(fmt(&formatted, 80), formatted);
+        formatted
+    }
+
+    #[test]
+    fn removes_redundant_parens() {
+        assert_eq!(roundtrip("((1 + 2))"), "1 + 2");
+        assert_eq!(roundtrip("1 + (2 * 3)"), "1 + 2 * 3");
+        assert_eq!(roundtrip("(1 - 2) - 3"), "1 - 2 - 3");
+        assert_eq!(roundtrip("2 ** (3 ** 4)"), "2 ** 3 ** 4");
+        assert_eq!(roundtrip("(f)(x)"), "f(x)");
+    }
+
+    #[test]
+    fn keeps_required_parens() {
+        assert_eq!(roundtrip("(1 + 2) * 3"), "(1 + 2) * 3");
+        assert_eq!(roundtrip("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(roundtrip("(2 ** 3) ** 4"), "(2 ** 3) ** 4");
+        assert_eq!(roundtrip("-(1 + 2)"), "-(1 + 2)");
+        assert_eq!(roundtrip("(fn x => x)(1)"), "(fn x => x)(1)");
+        assert_eq!(roundtrip("1 + (fn x => x)(2)"), "1 + (fn x => x)(2)");
+        assert_eq!(roundtrip("(1 + 2) as Float"), "(1 + 2) as Float");
+        assert_eq!(roundtrip("(-x as Float) * 2.0"), "-x as Float * 2.0");
+    }
+
+    #[test]
+    fn keeps_else_with_its_if() {
+        assert_eq!(roundtrip("if a then (if b then 1) else 2"), "if a then (if b then 1) else 2");
+        assert_eq!(roundtrip("if a then if b then 1 else 2"), "if a then if b then 1 else 2");
+    }
+
+    #[test]
+    fn roundtrips_every_construct() {
+        roundtrip("{ let x = 5; let y = x + 1; y; }");
+        roundtrip("fn (x: Int, y) => x + y -> Int");
+        roundtrip("fn x => (fn y => y) -> fn (Int) -> Int");
+        roundtrip("f(1, (2, 3), (4,))");
+        roundtrip("((1, 2), 3)");
+        roundtrip("!true == false");
+        roundtrip("\"hello\" ++ \"world\"");
+        roundtrip("fn (x: Float) => x * 2.5e-3 + 1e300 -> Float");
+        roundtrip("{ type Option<a> = None | Some(a); match Some(1) { Some(x) => x, None => 0 } }");
+        roundtrip("{ type Pair = Pair(Int, (Bool, String)); {} }");
+        roundtrip("let id = fn x => x in (let y = id(1) in y) + 1");
+        roundtrip("{ let r = { x = 1, y = (true, 2) }; { r | x = r.x + 1 }.y }");
+        roundtrip("fn (r: { x: Int, y: Bool }) => -r.x");
+        roundtrip("{ class Show a; instance Show Int; instance Show a => Show Option<a>; instance (Show a, Show b) => Show (a, b); 1 }");
+    }
+
+    #[test]
+    fn breaks_blocks_that_do_not_fit() {
+        assert_eq!(fmt("{ let x = 5; x }", 80), "{ let x = 5; x }");
+        assert_eq!(fmt("{ let x = 5; let y = x + 1; y }", 20), "{\n    let x = 5;\n    let y = x + 1;\n    y\n}");
+    }
+
+    /// Formats from the concrete syntax tree, checking that formatting again changes nothing
+    fn fmt_cst(src: &str, width: usize) -> String {
+        let formatted = Printer::new(width).print_cst(&crate::parse_cst(src).unwrap()).unwrap();
+        assert_eq!(Printer::new(width).print_cst(&crate::parse_cst(&formatted).unwrap()).unwrap(), formatted);
+        formatted
+    }
+
+    #[test]
+    fn keeps_comments() {
+        assert_eq!(fmt_cst("// leading\n{ let x = 5; // five\n /* the answer */ x   +  1 }\n// end", 80),
+            "// leading\n{\n    let x = 5; // five\n    /* the answer */ x + 1\n} // end");
+        assert_eq!(fmt_cst("f(1 /* one */, 2)", 80), "f(1 /* one */, 2)");
+        assert_eq!(fmt_cst("{\n    // first\n    let x = 1;\n    x // last\n}", 80), "{\n    // first\n    let x = 1;\n    x // last\n}");
+        assert_eq!(fmt_cst("let x = (1 // one\n) in x", 80), "let x =\n    1 // one\nin x");
+        assert_eq!(fmt_cst("{ let x = 1;\n/* note */\nx }", 80), "{\n    let x = 1;\n    /* note */\n    x\n}");
+    }
+
+    #[test]
+    fn breaks_arguments_that_do_not_fit() {
+        assert_eq!(fmt("function(argument, another)", 20), "function(\n    argument,\n    another\n)");
+    }
+}
@@ -0,0 +1,3 @@
+mod source_map;
+
+pub use source_map::{SourceMap, SourceFile, FileId, Loc};
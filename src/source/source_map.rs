@@ -0,0 +1,181 @@
+use crate::parsing::Span;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FileId(pub usize);
+
+/// A zero indexed line and character column
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Loc {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Loc {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    pub id: FileId,
+    pub name: String,
+    pub src: String,
+    /// The position of the file's first byte within the source map
+    pub start: usize,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(id: FileId, name: String, src: String, start: usize) -> Self {
+        let line_starts = std::iter::once(0).chain(src.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        Self { id, name, src, start, line_starts }
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.src.len()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The text of a line without its newline
+    pub fn line(&self, line: usize) -> &str {
+        match self.line_starts.get(line) {
+            Some(&lo) => {
+                let hi = self.line_starts.get(line + 1).map_or(self.src.len(), |&next| next - 1);
+                &self.src[lo..hi]
+            }
+            None => "",
+        }
+    }
+
+    /// The location of a position within the source map
+    /// Columns count characters rather than bytes and positions past the end are clamped to it
+    pub fn loc(&self, pos: usize) -> Loc {
+        let pos = pos.max(self.start).min(self.end()) - self.start;
+        let line = match self.line_starts.binary_search(&pos) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        Loc::new(line, self.src[self.line_starts[line]..pos].chars().count())
+    }
+
+    /// The position of a location, clamping columns past the end of the line to the line's end
+    pub fn pos(&self, loc: Loc) -> usize {
+        if loc.line >= self.line_count() { return self.end() }
+        let lo = self.line_starts[loc.line];
+        let line = self.line(loc.line);
+        let offset = line.char_indices().nth(loc.col).map_or(line.len(), |(i, _)| i);
+        self.start + lo + offset
+    }
+
+    /// The location of a position with the column counted in utf-16 code units as the language server protocol expects
+    pub fn utf16_loc(&self, pos: usize) -> Loc {
+        let loc = self.loc(pos);
+        Loc::new(loc.line, self.line(loc.line).chars().take(loc.col).map(char::len_utf16).sum())
+    }
+
+    /// The position of a location whose column is counted in utf-16 code units
+    pub fn utf16_pos(&self, loc: Loc) -> usize {
+        let mut units = 0;
+        let col = self.line(loc.line).chars().take_while(|c| {
+            units += c.len_utf16();
+            units <= loc.col
+        }).count();
+        self.pos(Loc::new(loc.line, col))
+    }
+
+    /// The locations of the start and end of a span, which may be on different lines
+    pub fn span_locs(&self, span: Span) -> (Loc, Loc) {
+        (self.loc(span.lo), self.loc(span.hi))
+    }
+}
+
+/// Holds every source file of a session
+/// Files occupy disjoint ranges of positions so a span alone determines its file
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file whose positions start after those of every existing file
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len());
+        // Leave a gap so the end of one file is not the start of the next
+        let start = self.files.last().map_or(0, |file| file.end() + 1);
+        self.files.push(SourceFile::new(id, name.into(), src.into(), start));
+        id
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// The file containing the position, if any
+    pub fn lookup(&self, pos: usize) -> Option<&SourceFile> {
+        self.files.iter().find(|file| file.start <= pos && pos <= file.end())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn maps_positions_to_locs_and_back() {
+        let mut map = SourceMap::new();
+        let id = map.add("main", "let x = 5;\nλ + x\n");
+        let file = map.file(id);
+        assert_eq!(file.loc(0), Loc::new(0, 0));
+        assert_eq!(file.loc(11), Loc::new(1, 0));
+        // `+` follows the two byte λ
+        assert_eq!(file.loc(14), Loc::new(1, 2));
+        assert_eq!(file.pos(Loc::new(1, 2)), 14);
+        assert_eq!(file.pos(Loc::new(0, 99)), 10);
+        assert_eq!(file.loc(file.end()), Loc::new(2, 0));
+        assert_eq!(file.line(1), "λ + x");
+    }
+
+    #[test]
+    fn counts_utf16_columns() {
+        let mut map = SourceMap::new();
+        let id = map.add("main", "\"😀\" + x\nλ");
+        let file = map.file(id);
+        // The emoji is four bytes, one char and two utf-16 code units
+        assert_eq!(file.loc(6), Loc::new(0, 3));
+        assert_eq!(file.utf16_loc(6), Loc::new(0, 4));
+        assert_eq!(file.utf16_pos(Loc::new(0, 4)), 6);
+        assert_eq!(file.utf16_pos(Loc::new(0, 99)), 10);
+        assert_eq!(file.utf16_loc(file.end()), Loc::new(1, 1));
+    }
+
+    #[test]
+    fn spans_cover_lines() {
+        let mut map = SourceMap::new();
+        let id = map.add("main", "{ 1;\n 2 }");
+        assert_eq!(map.file(id).span_locs(Span::new(0, 9)), (Loc::new(0, 0), Loc::new(1, 4)));
+    }
+
+    #[test]
+    fn files_have_disjoint_positions() {
+        let mut map = SourceMap::new();
+        let a = map.add("a", "abc");
+        let b = map.add("b", "de\nf");
+        assert_eq!(map.file(b).start, 4);
+        assert_eq!(map.lookup(2).map(|file| file.id), Some(a));
+        assert_eq!(map.lookup(7).map(|file| file.id), Some(b));
+        assert_eq!(map.file(b).loc(7), Loc::new(1, 0));
+        assert_eq!(map.file(b).pos(Loc::new(1, 1)), 8);
+    }
+}
@@ -0,0 +1,19 @@
+use crate::parsing::Span;
+
+/// The typechecker's view of a declared algebraic data type
+/// The constructors themselves are bound in the environment as regular values
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AdtDef {
+    /// Number of type parameters
+    pub arity: usize,
+    /// Constructor names with the number of fields each takes
+    pub ctors: Vec<(String, usize)>,
+    /// Where the type was declared
+    pub span: Span,
+}
+
+impl AdtDef {
+    pub fn new(arity: usize, ctors: Vec<(String, usize)>, span: Span) -> Self {
+        Self { arity, ctors, span }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::parsing::{Expr, ExprKind};
+use crate::error::Error;
+use super::{Typechecker, TyKind, Ty, Constraint};
+
+impl<'a> Typechecker<'a> {
+    /// Checks the expression against the type expected of it
+    /// Expressions that cannot make use of the expected type are inferred and the mismatch is reported at the expression
+    pub(super) fn check(&mut self, expr: &'a mut Expr, expected: &Ty) -> Result<Constraint, Error> {
+        let ty = expr.ty.clone();
+        let (t, c) = self.check_expr(expr, expected)?;
+        Ok(Constraint::conj(vec![c, Constraint::Eq(t.clone(), expected.clone()), Constraint::Eq(t, ty)]))
+    }
+
+    fn check_expr(&mut self, expr: &'a mut Expr, expected: &Ty) -> Result<(Ty, Constraint), Error> {
+        if !Self::is_checkable(&expr.kind, expected) {
+            // The expected parameter types become the annotations of the lambda where it has none
+            // and its body is checked against the expected return type unless it has its own
+            let mut constraints = vec![];
+            let mut expected_ret = None;
+            if let (ExprKind::Lambda { params, ret, .. }, TyKind::Arrow(box Ty { kind: TyKind::Tuple(tparams), .. }, box tret)) = (&mut expr.kind, &expected.kind) {
+                if params.len() == tparams.len() {
+                    for (binder, t) in params.iter_mut().zip(tparams) {
+                        match binder.ty.kind {
+                            TyKind::Infer(_) => binder.ty = t.clone(),
+                            _ => constraints.push(Constraint::Eq(binder.ty.clone(), t.clone())),
+                        }
+                    }
+                    match ret {
+                        Some(ret) => constraints.push(Constraint::Eq(ret.clone(), tret.clone())),
+                        None => expected_ret = Some(tret.clone()),
+                    }
+                }
+            }
+            let (span, ty) = (expr.span, expr.ty.clone());
+            let (t, c) = match expected_ret {
+                Some(ret) => match &mut expr.kind {
+                    ExprKind::Lambda { params, body, .. } => self.infer_lambda(params, &ret, body, span, ty)?,
+                    _ => unreachable!(),
+                },
+                None => self.infer_expr(expr)?,
+            };
+            constraints.push(c);
+            return Ok((t, Constraint::conj(constraints)))
+        }
+
+        let ty = expr.ty.clone();
+        let span = expr.span;
+        match &mut expr.kind {
+            ExprKind::Grouping { expr } => Ok((expected.clone(), self.check(expr, expected)?)),
+            ExprKind::Tuple { elems } => {
+                let tys = match &expected.kind { TyKind::Tuple(tys) => tys, _ => unreachable!() };
+                let cs = elems.iter_mut().zip(tys).map(|(elem, t)| self.check(elem, t)).collect::<Result<Vec<_>, _>>()?;
+                Ok((expected.clone(), Constraint::conj(cs)))
+            }
+            ExprKind::Record { fields } => {
+                Self::check_fields(fields)?;
+                let tys = match &expected.kind { TyKind::Record(tys, None) => tys, _ => unreachable!() };
+                let cs = fields.iter_mut().map(|field| {
+                    let (_, t) = tys.iter().find(|(name, _)| *name == field.name).unwrap();
+                    self.check(&mut field.value, t)
+                }).collect::<Result<Vec<_>, _>>()?;
+                Ok((expected.clone(), Constraint::conj(cs)))
+            }
+            ExprKind::If { cond, then, otherwise: Some(otherwise) } => {
+                let cond_span = cond.span;
+                let (tcond, ccond) = self.infer(cond)?;
+                let c_cond_is_bool = Constraint::Eq(tcond, Ty::new(cond_span, TyKind::Bool));
+                let cthen = self.check(then, expected)?;
+                let cotherwise = self.check(otherwise, expected)?;
+                Ok((expected.clone(), Constraint::conj(vec![ccond, c_cond_is_bool, cthen, cotherwise])))
+            }
+            ExprKind::Block { exprs, suppressed } => Ok(self.infer_block(exprs, *suppressed, span, Some(expected))),
+            ExprKind::Match { scrutinee, arms } => self.infer_match(scrutinee, arms, &ty, span, Some(expected)),
+            ExprKind::LetIn { binder, bound, body } => self.infer_let_in(binder, bound, body, Some(expected)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether the expected type can be pushed into the subexpressions
+    fn is_checkable(kind: &ExprKind, expected: &Ty) -> bool {
+        match (kind, &expected.kind) {
+            (ExprKind::Tuple { elems }, TyKind::Tuple(tys)) => elems.len() == tys.len(),
+            // Fields are only checked against a closed record with exactly the same fields
+            (ExprKind::Record { fields }, TyKind::Record(tys, None)) =>
+                fields.len() == tys.len() && fields.iter().all(|field| tys.iter().any(|(name, _)| *name == field.name)),
+            (ExprKind::If { otherwise, .. }, _) => otherwise.is_some(),
+            (ExprKind::Grouping { .. }, _) | (ExprKind::Block { .. }, _) | (ExprKind::Match { .. }, _) | (ExprKind::LetIn { .. }, _) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::driver::Session;
+    use crate::error::Error;
+    use super::super::TypecheckMode;
+    use super::*;
+
+    fn typecheck(src: &str, mode: TypecheckMode) -> Result<Ty, Vec<Error>> {
+        let mut session = Session::new().with_mode(mode);
+        let file = session.add_file("", src);
+        session.compile(file).into_result().map(|(ty, _)| ty)
+    }
+
+    #[test]
+    fn agrees_with_constraints() {
+        let srcs = [
+            "{ let id = fn x => x; (id(1), id(true)) }",
+            "{ let square = fn x => x * x; (square(1.5), square(2)) }",
+            "{ let f: fn (Int) -> Int = fn x => x; f }",
+            "{ let apply = fn (f: fn (Int) -> Int, x) => f(x); apply(fn y => y + 1, 2) }",
+            "{ type Option<a> = None | Some(a); let x: Option<(Int, Bool)> = match 1 { 1 => Some((2, true)), _ => None }; x }",
+            "fn f => fn x => f(f(x))",
+        ];
+        for src in &srcs {
+            assert_eq!(typecheck(src, TypecheckMode::Bidirectional).unwrap(), typecheck(src, TypecheckMode::Constraints).unwrap(), "{}", src)
+        }
+    }
+
+    #[test]
+    fn reports_mismatch_at_checked_expression() {
+        let src = "fn x => (if x then 1 else true) -> Int";
+        let err = typecheck(src, TypecheckMode::Bidirectional).unwrap_err().remove(0);
+        assert_eq!((err.span.lo, err.labels[0].span.lo), (26, 35));
+        // The branches are equated with each other before the annotation is considered
+        let err = typecheck(src, TypecheckMode::Constraints).unwrap_err().remove(0);
+        assert_eq!((err.span.lo, err.labels[0].span.lo), (19, 26));
+    }
+
+    #[test]
+    fn pushes_expected_type_through_blocks_and_arms() {
+        let err = typecheck("{ let x: Int = { let y = 1; if true then y else false }; x }", TypecheckMode::Bidirectional).unwrap_err().remove(0);
+        assert_eq!((err.span.lo, err.labels[0].span.lo), (48, 9));
+        let err = typecheck("{ let x: (Int, Int) = match 1 { 1 => (1, 2), _ => (3, \"4\") }; x }", TypecheckMode::Bidirectional).unwrap_err().remove(0);
+        assert_eq!(err.span.lo, 54);
+        let err = typecheck("{ let r: { x: Int, y: Bool } = { x = 1, y = 2 }; r }", TypecheckMode::Bidirectional).unwrap_err().remove(0);
+        assert_eq!((err.span.lo, err.labels[0].span.lo), (44, 22));
+    }
+}
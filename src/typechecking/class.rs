@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use crate::parsing::Span;
+use super::{Ty, TyKind, Type, Substitution};
+
+/// The builtin classes the operators are overloaded through
+pub(crate) const NUM: &str = "Num";
+pub(crate) const ORD: &str = "Ord";
+pub(crate) const EQ: &str = "Eq";
+
+/// An assertion that a type is an instance of a class such as `Num a`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Predicate {
+    pub class: String,
+    pub ty: Ty,
+}
+
+impl Predicate {
+    pub fn new(class: impl Into<String>, ty: Ty) -> Self {
+        Self { class: class.into(), ty }
+    }
+}
+
+impl Type for Predicate {
+    fn apply(&mut self, s: &Substitution) { self.ty.apply(s) }
+    fn ftv(&self) -> HashSet<u64> { self.ty.ftv() }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result { write!(f, "{} {}", self.class, self.ty) }
+}
+
+/// The head and context of an instance refer to the instance's type variables by name
+#[derive(Clone, Debug)]
+pub(crate) struct Instance {
+    pub context: Vec<Predicate>,
+    pub head: Ty,
+    /// Builtin and derived instances have no declaration
+    pub span: Option<Span>,
+}
+
+impl Instance {
+    pub fn new(context: Vec<Predicate>, head: Ty, span: Option<Span>) -> Self {
+        Self { context, head, span }
+    }
+
+    /// The predicates the instance requires to hold for `ty`
+    /// None if the head does not match and `Err` if it is unknown whether it matches until inference variables are solved
+    fn requires(&self, ty: &Ty) -> Result<Option<Vec<Predicate>>, ()> {
+        let mut bindings = HashMap::new();
+        match matches(&self.head, ty, &mut bindings) {
+            Some(true) => Ok(Some(self.context.iter()
+                .map(|pred| Predicate::new(pred.class.clone(), substitute(&pred.ty, &bindings).with_span(ty.span)))
+                .collect())),
+            Some(false) => Ok(None),
+            None => Err(()),
+        }
+    }
+}
+
+/// Declared classes along with their instances
+#[derive(Debug, Clone)]
+pub(crate) struct ClassEnv {
+    classes: HashMap<String, Vec<Instance>>,
+}
+
+impl ClassEnv {
+    /// Starts with the builtin classes and their instances
+    /// Equality on tuples is structural and equality on data types is derived when they are declared
+    pub fn new() -> Self {
+        let mut env = Self { classes: HashMap::new() };
+        let builtin = |kind| Instance::new(vec![], Ty::new(Span::single(0), kind), None);
+        for class in &[NUM, ORD, EQ] { env.declare(class) }
+        for kind in &[TyKind::I64, TyKind::F64] {
+            env.add_instance(NUM, builtin(kind.clone()));
+            env.add_instance(ORD, builtin(kind.clone()));
+            env.add_instance(EQ, builtin(kind.clone()));
+        }
+        env.add_instance(EQ, builtin(TyKind::Bool));
+        env.add_instance(EQ, builtin(TyKind::Str));
+        env
+    }
+
+    pub fn contains(&self, class: &str) -> bool {
+        self.classes.contains_key(class)
+    }
+
+    pub fn declare(&mut self, class: &str) {
+        self.classes.insert(class.to_owned(), vec![]);
+    }
+
+    pub fn add_instance(&mut self, class: &str, instance: Instance) {
+        self.classes.get_mut(class).expect("instance of undeclared class").push(instance)
+    }
+
+    /// Adds the instance in place of any instance it overlaps so redeclared data types replace their derived instances
+    pub fn derive(&mut self, class: &str, instance: Instance) {
+        let instances = self.classes.get_mut(class).expect("instance of undeclared class");
+        instances.retain(|existing| !overlaps(&existing.head, &instance.head));
+        instances.push(instance);
+    }
+
+    /// An existing instance that applies to some of the same types as the predicate
+    pub fn overlapping(&self, pred: &Predicate) -> Option<&Instance> {
+        self.classes.get(&pred.class)?.iter().find(|instance| overlaps(&instance.head, &pred.ty))
+    }
+
+    /// Reduces the predicate to the predicates on unsolved types it depends on
+    /// Returns the predicate that has no instance on failure
+    pub fn reduce(&self, pred: Predicate) -> Result<Vec<Predicate>, Predicate> {
+        self.reduce_in(pred, &mut vec![])
+    }
+
+    /// `seen` holds the predicates being proven so recursive data types may assume them
+    fn reduce_in(&self, pred: Predicate, seen: &mut Vec<Predicate>) -> Result<Vec<Predicate>, Predicate> {
+        // The missing fields of an open record may still be solved
+        if let TyKind::Infer(_) | TyKind::Record(_, Some(_)) = pred.ty.kind { return Ok(vec![pred]) }
+        if seen.contains(&pred) { return Ok(vec![]) }
+        let required = match &pred.ty.kind {
+            TyKind::Tuple(xs) if pred.class == EQ => xs.iter().map(|t| Predicate::new(EQ, t.with_span(pred.ty.span))).collect(),
+            TyKind::Record(fields, None) if pred.class == EQ => fields.iter().map(|(_, t)| Predicate::new(EQ, t.with_span(pred.ty.span))).collect(),
+            _ => {
+                let instances = self.classes.get(&pred.class).map_or(&[][..], |instances| instances.as_slice());
+                let mut undecided = false;
+                let mut required = None;
+                for instance in instances {
+                    match instance.requires(&pred.ty) {
+                        Ok(Some(preds)) => { required = Some(preds); break }
+                        Ok(None) => {}
+                        Err(()) => undecided = true,
+                    }
+                }
+                match required {
+                    Some(required) => required,
+                    None if undecided => return Ok(vec![pred]),
+                    None => return Err(pred),
+                }
+            }
+        };
+        seen.push(pred);
+        let mut reduced = vec![];
+        for pred in required { reduced.extend(self.reduce_in(pred, seen)?) }
+        Ok(reduced)
+    }
+}
+
+/// The names of the type variables in the type in order of appearance
+pub(crate) fn type_params(ty: &Ty) -> Vec<String> {
+    fn go(ty: &Ty, params: &mut Vec<String>) {
+        match &ty.kind {
+            TyKind::TyVar(name) => if !params.contains(name) { params.push(name.clone()) },
+            TyKind::Tuple(xs) | TyKind::Adt(_, xs) => xs.iter().for_each(|t| go(t, params)),
+            TyKind::Arrow(l, r) => { go(l, params); go(r, params) }
+            _ => {}
+        }
+    }
+    let mut params = vec![];
+    go(ty, &mut params);
+    params
+}
+
+/// Matches the instance head against the type, binding the head's type variables
+/// None if the type has an inference variable where the head has a type constructor
+fn matches(head: &Ty, ty: &Ty, bindings: &mut HashMap<String, Ty>) -> Option<bool> {
+    let all = |xs: &[Ty], ys: &[Ty], bindings: &mut HashMap<String, Ty>| {
+        let mut result = Some(true);
+        for (x, y) in xs.iter().zip(ys) {
+            match matches(x, y, bindings) {
+                Some(false) => return Some(false),
+                None => result = None,
+                Some(true) => {}
+            }
+        }
+        result
+    };
+    match (&head.kind, &ty.kind) {
+        (TyKind::TyVar(name), _) => match bindings.get(name) {
+            Some(bound) => Some(bound == ty),
+            None => {
+                bindings.insert(name.clone(), ty.clone());
+                Some(true)
+            }
+        },
+        (_, TyKind::Infer(_)) => None,
+        (TyKind::Tuple(xs), TyKind::Tuple(ys)) if xs.len() == ys.len() => all(xs, ys, bindings),
+        (TyKind::Adt(n, xs), TyKind::Adt(m, ys)) if n == m && xs.len() == ys.len() => all(xs, ys, bindings),
+        (TyKind::Arrow(l, r), TyKind::Arrow(t, u)) => all(&[*l.clone(), *r.clone()], &[*t.clone(), *u.clone()], bindings),
+        (x, y) => Some(x == y),
+    }
+}
+
+/// Whether some type is matched by both instance heads
+fn overlaps(t: &Ty, u: &Ty) -> bool {
+    let all = |xs: &[Ty], ys: &[Ty]| xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| overlaps(x, y));
+    match (&t.kind, &u.kind) {
+        (TyKind::TyVar(_), _) | (_, TyKind::TyVar(_)) => true,
+        (TyKind::Tuple(xs), TyKind::Tuple(ys)) => all(xs, ys),
+        (TyKind::Adt(n, xs), TyKind::Adt(m, ys)) => n == m && all(xs, ys),
+        (TyKind::Arrow(l, r), TyKind::Arrow(t, u)) => overlaps(l, t) && overlaps(r, u),
+        (x, y) => x == y,
+    }
+}
+
+fn substitute(ty: &Ty, bindings: &HashMap<String, Ty>) -> Ty {
+    let kind = match &ty.kind {
+        TyKind::TyVar(name) => return bindings.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        TyKind::Tuple(xs) => TyKind::Tuple(xs.iter().map(|t| substitute(t, bindings)).collect()),
+        TyKind::Adt(name, xs) => TyKind::Adt(name.clone(), xs.iter().map(|t| substitute(t, bindings)).collect()),
+        TyKind::Arrow(l, r) => TyKind::Arrow(Box::new(substitute(l, bindings)), Box::new(substitute(r, bindings))),
+        kind => kind.clone(),
+    };
+    Ty::new(ty.span, kind)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ty(kind: TyKind) -> Ty { Ty::new(Span::single(0), kind) }
+    fn var(name: &str) -> Ty { ty(TyKind::TyVar(name.to_owned())) }
+
+    #[test]
+    fn reduces_through_instance_contexts() {
+        let mut env = ClassEnv::new();
+        let option = |t| ty(TyKind::Adt("Option".to_owned(), vec![t]));
+        env.derive(EQ, Instance::new(vec![Predicate::new(EQ, var("a"))], option(var("a")), None));
+        assert_eq!(env.reduce(Predicate::new(EQ, option(ty(TyKind::Tuple(vec![ty(TyKind::I64), ty(TyKind::Bool)]))))), Ok(vec![]));
+        assert_eq!(env.reduce(Predicate::new(EQ, option(ty(TyKind::Infer(0))))), Ok(vec![Predicate::new(EQ, ty(TyKind::Infer(0)))]));
+        let f = ty(TyKind::Arrow(Box::new(ty(TyKind::I64)), Box::new(ty(TyKind::I64))));
+        assert_eq!(env.reduce(Predicate::new(EQ, option(f.clone()))), Err(Predicate::new(EQ, f)));
+        assert_eq!(env.reduce(Predicate::new(NUM, ty(TyKind::Bool))), Err(Predicate::new(NUM, ty(TyKind::Bool))));
+    }
+
+    #[test]
+    fn assumes_predicates_of_recursive_types() {
+        let mut env = ClassEnv::new();
+        let list = |t| ty(TyKind::Adt("List".to_owned(), vec![t]));
+        env.derive(EQ, Instance::new(vec![Predicate::new(EQ, var("a")), Predicate::new(EQ, list(var("a")))], list(var("a")), None));
+        assert_eq!(env.reduce(Predicate::new(EQ, list(ty(TyKind::I64)))), Ok(vec![]));
+    }
+
+    #[test]
+    fn detects_overlap() {
+        let env = ClassEnv::new();
+        assert!(env.overlapping(&Predicate::new(NUM, ty(TyKind::I64))).is_some());
+        assert!(env.overlapping(&Predicate::new(NUM, ty(TyKind::Bool))).is_none());
+        assert!(overlaps(&ty(TyKind::Tuple(vec![var("a"), ty(TyKind::I64)])), &ty(TyKind::Tuple(vec![ty(TyKind::Bool), var("b")]))));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Ty, Type, Substitution};
+use super::{Ty, Type, Substitution, Predicate};
 use std::fmt::{self, Formatter, Display};
 use std::collections::HashSet;
 
@@ -6,6 +6,8 @@ use std::collections::HashSet;
 pub enum Constraint {
     Empty,
     Eq(Ty, Ty),
+    /// The type must be an instance of the class, which is checked once the equalities are solved
+    Pred(Predicate),
     And(Box<Constraint>, Box<Constraint>)
 }
 
@@ -16,6 +18,7 @@ impl Type for Constraint {
         match self {
             Empty     => HashSet::new(),
             Eq(t, u)  => &t.ftv() | &u.ftv(),
+            Pred(p)   => p.ftv(),
             And(c, d) => &c.ftv() | &d.ftv(),
         }
     }
@@ -24,6 +27,7 @@ impl Type for Constraint {
         match self {
             Empty     => {}
             Eq(t, u)  => { t.apply(s); u.apply(s) },
+            Pred(p)   => p.apply(s),
             And(c, d) => { c.apply(s); d.apply(s) },
         }
     }
@@ -31,8 +35,30 @@ impl Type for Constraint {
 }
 
 impl Constraint {
-    pub fn conj(cs: Vec<Constraint>) -> Constraint {
-        cs.into_iter().fold(Self::Empty, |acc, x| Self::And(box acc, box x))
+    /// The conjunction is balanced so long blocks do not produce deeply nested constraints
+    pub fn conj(mut cs: Vec<Constraint>) -> Constraint {
+        match cs.len() {
+            0 => Self::Empty,
+            1 => cs.pop().unwrap(),
+            n => {
+                let right = cs.split_off(n / 2);
+                Self::And(Box::new(Self::conj(cs)), Box::new(Self::conj(right)))
+            }
+        }
+    }
+
+    /// Separates the class predicates from the equalities
+    pub fn partition(self) -> (Constraint, Vec<Predicate>) {
+        fn go(c: Constraint, preds: &mut Vec<Predicate>) -> Constraint {
+            match c {
+                Pred(p)   => { preds.push(p); Empty }
+                And(c, d) => And(Box::new(go(*c, preds)), Box::new(go(*d, preds))),
+                c         => c,
+            }
+        }
+        let mut preds = vec![];
+        let c = go(self, &mut preds);
+        (c, preds)
     }
 }
 
@@ -41,6 +67,7 @@ impl Display for Constraint {
         match self {
             Empty                     => write!(f, ""),
             Eq(t, u)                  => write!(f, "{} ~ {}", t, u),
+            Pred(p)                   => write!(f, "{}", p),
             And(box Empty, box Empty) => write!(f, ""),
             And(box c, box Empty)     => write!(f, "{}", c),
             And(box Empty, box c)     => write!(f, "{}", c),
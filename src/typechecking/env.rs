@@ -1,12 +1,12 @@
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::borrow::Borrow;
 use std::fmt::Debug;
 use super::{Type, TyScheme, Substitution};
 
 #[derive(Debug, PartialEq)]
 pub(crate) struct Env<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
     contexts: Vec<Ctx<K, V>>,
-    saved: usize,
 }
 
 impl<K> Type for Env<K, TyScheme> where K : Hash + Eq + Debug {
@@ -14,29 +14,24 @@ impl<K> Type for Env<K, TyScheme> where K : Hash + Eq + Debug {
         self.contexts.iter().map(|t| t.ftv()).fold(HashSet::new(), |acc, x| &acc | &x)
     }
 
-    fn apply(&mut self, _s: &Substitution) { unimplemented!() }
+    fn apply(&mut self, s: &Substitution) {
+        self.contexts.iter_mut().for_each(|ctx| ctx.apply(s))
+    }
 }
 
-impl<'a, K, V> Env<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
+impl<K, V> Env<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
 
     pub fn new() -> Self {
-        Self { contexts: vec![Ctx::new()], saved: 1 }
+        Self { contexts: vec![Ctx::new()] }
     }
 
     pub fn define(&mut self, k: K, v: V) { self.contexts.last_mut().unwrap().insert(k, v) }
 
-    /// Saves a scope as a restore point
-    pub fn save(&mut self) { self.saved = self.contexts.len() }
-
-    /// Restore pops every scope after the saved one
-    /// Saved scope is NOT removed
-    pub fn restore(&mut self) { self.contexts.drain(self.saved..); }
-
     pub fn push(&mut self) { self.contexts.push(Ctx::new()) }
 
     pub fn pop(&mut self) { self.contexts.pop(); }
 
-    pub fn lookup(&self, k: &K) -> Option<&V> {
+    pub fn lookup<Q>(&self, k: &Q) -> Option<&V> where K : Borrow<Q>, Q : Hash + Eq + ?Sized {
         for ctx in self.contexts.iter().rev() {
             if let Some(v) = ctx.lookup(k) { return Some(v) }
         }
@@ -60,7 +55,7 @@ impl<K> Type for Ctx<K, TyScheme> where K : Hash + Eq + Debug {
     }
 }
 
-impl<'a, K, V> Ctx<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
+impl<K, V> Ctx<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
 
     pub fn new() -> Self {
         Self { ctx: HashMap::new() }
@@ -70,7 +65,7 @@ impl<'a, K, V> Ctx<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
         self.ctx.insert(k, v);
     }
 
-    pub fn lookup(&self, k: &K) -> Option<&V> {
+    pub fn lookup<Q>(&self, k: &Q) -> Option<&V> where K : Borrow<Q>, Q : Hash + Eq + ?Sized {
         self.ctx.get(k)
     }
 
@@ -80,25 +75,39 @@ impl<'a, K, V> Ctx<K, V> where K : Hash + Eq + Debug, V : Debug + PartialEq {
 mod test {
 
     use super::*;
+    use super::super::{Ty, TyKind};
+    use crate::parsing::Span;
 
     #[test]
     fn test() {
         let mut env = Env::new();
-        env.push();
         env.define(0, 0);
         env.push();
-        env.save();
+        env.define(0, 1);
         env.define(1, 1);
+        assert_eq!(env.lookup(&0), Some(&1));
         env.push();
-        env.define(100, 100);
-        env.push();
-        env.define(200, 200);
-        env.restore();
+        env.define(2, 2);
         env.pop();
+        assert_eq!(env.lookup(&2), None);
         env.pop();
-        env.save();
+        assert_eq!(env.lookup(&0), Some(&0));
+        assert_eq!(env.lookup(&1), None);
+    }
 
-        assert_eq!(env, Env::new());
+    #[test]
+    fn apply_substitutes_every_scope() {
+        let ty = |kind| Ty::new(Span::single(0), kind);
+        let mut env = Env::new();
+        env.define("x", TyScheme::from(ty(TyKind::Infer(0))));
+        env.push();
+        env.define("y", TyScheme::new(ty(TyKind::Infer(1)), std::iter::once(1).collect()));
+        let s = vec![(0, ty(TyKind::I64)), (1, ty(TyKind::Bool))].into_iter().collect();
+        env.apply(&s);
+        assert_eq!(env.lookup(&"x"), Some(&TyScheme::from(ty(TyKind::I64))));
+        // Quantified variables are not substituted
+        assert_eq!(env.lookup(&"y"), Some(&TyScheme::new(ty(TyKind::Infer(1)), std::iter::once(1).collect())));
+        assert!(env.ftv().is_empty());
     }
 }
 
@@ -0,0 +1,174 @@
+use std::fmt::{self, Display, Formatter};
+use crate::parsing::{Pattern, PatternKind};
+use super::{AdtDef, Env};
+
+/// Usefulness based exhaustiveness checking (Maranget, "Warnings for pattern matching")
+/// Operates on patterns alone so it does not require the scrutinee's type to be solved
+/// The patterns are assumed to be well typed; ill typed columns are reported by unification instead
+pub(crate) struct Matrix<'a> {
+    adts: &'a Env<String, AdtDef>,
+    /// The name of the type a constructor in scope belongs to
+    adt_of: &'a dyn Fn(&str) -> Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Ctor {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Tuple(usize),
+    /// The name of the type and of the constructor
+    Adt(String, String),
+}
+
+/// Simplified patterns where binders are indistinguishable from wildcards
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+type Row = Vec<Pat>;
+
+impl<'a> Matrix<'a> {
+    pub fn new(adts: &'a Env<String, AdtDef>, adt_of: &'a dyn Fn(&str) -> Option<String>) -> Self {
+        Self { adts, adt_of }
+    }
+
+    /// Whether the pattern matches some value not matched by any of the previous patterns
+    pub fn is_useful(&self, prev: &[&Pattern], pat: &Pattern) -> bool {
+        let rows = prev.iter().map(|p| vec![self.lower(p)]).collect::<Vec<_>>();
+        self.useful(&rows, &[self.lower(pat)])
+    }
+
+    /// Returns a value not matched by any of the patterns if there is one
+    pub fn missing(&self, pats: &[&Pattern]) -> Option<Pat> {
+        let rows = pats.iter().map(|p| vec![self.lower(p)]).collect::<Vec<_>>();
+        self.witness(&rows, 1).map(|mut w| w.remove(0))
+    }
+
+    fn lower(&self, pat: &Pattern) -> Pat {
+        match &pat.kind {
+            PatternKind::Wildcard | PatternKind::Binding { .. } => Pat::Wild,
+            PatternKind::Integral { value }        => Pat::Ctor(Ctor::Int(*value), vec![]),
+            PatternKind::Bool { b }                => Pat::Ctor(Ctor::Bool(*b), vec![]),
+            PatternKind::Str { string }            => Pat::Ctor(Ctor::Str(string.clone()), vec![]),
+            PatternKind::Tuple { elems }           => Pat::Ctor(Ctor::Tuple(elems.len()), elems.iter().map(|p| self.lower(p)).collect()),
+            PatternKind::Constructor { name, args } => {
+                let adt = (self.adt_of)(name).unwrap_or_default();
+                Pat::Ctor(Ctor::Adt(adt, name.clone()), args.iter().map(|p| self.lower(p)).collect())
+            }
+        }
+    }
+
+    fn useful(&self, rows: &[Row], v: &[Pat]) -> bool {
+        let (head, rest) = match v.split_first() {
+            Some(split) => split,
+            None => return rows.is_empty(),
+        };
+        match head {
+            Pat::Ctor(ctor, args) => {
+                let v = args.iter().chain(rest).cloned().collect::<Vec<_>>();
+                self.useful(&self.specialize(rows, ctor), &v)
+            }
+            Pat::Wild => match self.complete_signature(rows) {
+                Some(ctors) => ctors.iter().any(|ctor| {
+                    let v = vec![Pat::Wild; self.arity(ctor)].into_iter().chain(rest.iter().cloned()).collect::<Vec<_>>();
+                    self.useful(&self.specialize(rows, ctor), &v)
+                }),
+                None => self.useful(&Self::default_rows(rows), rest),
+            }
+        }
+    }
+
+    /// Finds a vector of `n` patterns matched by no row
+    fn witness(&self, rows: &[Row], n: usize) -> Option<Row> {
+        if n == 0 { return if rows.is_empty() { Some(vec![]) } else { None } }
+        match self.complete_signature(rows) {
+            Some(ctors) => ctors.into_iter().find_map(|ctor| {
+                let arity = self.arity(&ctor);
+                let mut w = self.witness(&self.specialize(rows, &ctor), arity + n - 1)?;
+                let rest = w.split_off(arity);
+                Some(std::iter::once(Pat::Ctor(ctor, w)).chain(rest).collect())
+            }),
+            None => {
+                let w = self.witness(&Self::default_rows(rows), n - 1)?;
+                let head = match self.missing_ctor(rows) {
+                    Some(ctor) => Pat::Ctor(ctor.clone(), vec![Pat::Wild; self.arity(&ctor)]),
+                    None => Pat::Wild,
+                };
+                Some(std::iter::once(head).chain(w).collect())
+            }
+        }
+    }
+
+    fn head_ctors(rows: &[Row]) -> Vec<&Ctor> {
+        rows.iter().filter_map(|row| match &row[0] {
+            Pat::Ctor(ctor, _) => Some(ctor),
+            Pat::Wild => None,
+        }).collect()
+    }
+
+    /// Every constructor of the first column's type if all of them appear in the column
+    fn complete_signature(&self, rows: &[Row]) -> Option<Vec<Ctor>> {
+        let heads = Self::head_ctors(rows);
+        let all = self.all_ctors(heads.first()?)?;
+        if all.iter().all(|ctor| heads.contains(&ctor)) { Some(all) } else { None }
+    }
+
+    /// A constructor of the first column's type that does not appear in the column
+    fn missing_ctor(&self, rows: &[Row]) -> Option<Ctor> {
+        let heads = Self::head_ctors(rows);
+        self.all_ctors(heads.first()?)?.into_iter().find(|ctor| !heads.contains(&ctor))
+    }
+
+    /// All constructors of the type of `ctor`, or None if there are infinitely many
+    fn all_ctors(&self, ctor: &Ctor) -> Option<Vec<Ctor>> {
+        match ctor {
+            Ctor::Bool(_) => Some(vec![Ctor::Bool(false), Ctor::Bool(true)]),
+            Ctor::Tuple(n) => Some(vec![Ctor::Tuple(*n)]),
+            Ctor::Adt(adt, _) => self.adts.lookup(adt).map(|def| def.ctors.iter().map(|(c, _)| Ctor::Adt(adt.clone(), c.clone())).collect()),
+            Ctor::Int(_) | Ctor::Str(_) => None,
+        }
+    }
+
+    fn arity(&self, ctor: &Ctor) -> usize {
+        match ctor {
+            Ctor::Tuple(n) => *n,
+            Ctor::Adt(adt, name) => self.adts.lookup(adt)
+                .and_then(|adt| adt.ctors.iter().find(|(c, _)| c == name))
+                .map(|(_, n)| *n)
+                .unwrap_or(0),
+            Ctor::Bool(_) | Ctor::Int(_) | Ctor::Str(_) => 0,
+        }
+    }
+
+    /// Keeps the rows that can match `ctor` and expands the first column into its arguments
+    fn specialize(&self, rows: &[Row], ctor: &Ctor) -> Vec<Row> {
+        rows.iter().filter_map(|row| match &row[0] {
+            Pat::Ctor(c, args) if c == ctor => Some(args.iter().chain(&row[1..]).cloned().collect()),
+            Pat::Ctor(..) => None,
+            Pat::Wild => Some(vec![Pat::Wild; self.arity(ctor)].into_iter().chain(row[1..].iter().cloned()).collect()),
+        }).collect()
+    }
+
+    /// Keeps the rows with a wildcard in the first column and drops that column
+    fn default_rows(rows: &[Row]) -> Vec<Row> {
+        rows.iter().filter(|row| row[0] == Pat::Wild).map(|row| row[1..].to_vec()).collect()
+    }
+}
+
+impl Display for Pat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let fmt_args = |args: &Vec<Pat>| args.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+        match self {
+            Pat::Wild                           => write!(f, "_"),
+            Pat::Ctor(Ctor::Bool(b), _)         => write!(f, "{}", b),
+            Pat::Ctor(Ctor::Int(i), _)          => write!(f, "{}", i),
+            Pat::Ctor(Ctor::Str(s), _)          => write!(f, "\"{}\"", s),
+            Pat::Ctor(Ctor::Tuple(_), args)     => write!(f, "({})", fmt_args(args)),
+            Pat::Ctor(Ctor::Adt(_, name), args) if args.is_empty() => write!(f, "{}", name),
+            Pat::Ctor(Ctor::Adt(_, name), args) => write!(f, "{}({})", name, fmt_args(args)),
+        }
+    }
+}
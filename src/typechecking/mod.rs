@@ -4,13 +4,24 @@ mod env;
 mod constraint;
 mod substitution;
 mod tyscheme;
+mod adt;
+mod exhaustiveness;
+mod class;
+mod bidirectional;
+mod unifier;
 
 pub use typing::{Ty, TyKind};
-pub use typechecker::Typechecker;
+pub use typechecker::{Typechecker, TypecheckMode};
+pub use class::Predicate;
+pub(crate) use typechecker::Normalizer;
 pub(crate) use tyscheme::TyScheme;
 pub(crate) use env::Env;
+pub(crate) use adt::AdtDef;
+pub(crate) use exhaustiveness::Matrix;
+pub(crate) use class::{ClassEnv, Instance, type_params};
 pub(crate) use constraint::Constraint;
-pub(crate) use substitution::{Substitution, solve};
+pub(crate) use substitution::{Substitution, compose};
+pub(crate) use unifier::Unifier;
 
 
 use std::collections::HashSet;
@@ -1,55 +1,11 @@
 use std::collections::HashMap;
-use super::{TyKind, Constraint, Type, Ty};
-use crate::error::Error;
-use crate::map;
+use super::{Type, Ty};
 
 pub type Substitution = HashMap<u64, Ty>;
 
-pub(crate) fn solve(constraint: Constraint) -> Result<Substitution, Error> {
-    match constraint {
-        Constraint::Empty => Ok(HashMap::new()),
-        Constraint::And(box c, box mut d) => {
-            let s = solve(c)?;
-            d.apply(&s); // Apply substitution to constraint before continuing to avoid inconsistencies
-            let t = solve(d)?;
-            Ok(compose(s, t))
-        }
-        Constraint::Eq(t, u) => unify(t, u)
-    }
-}
-
-fn unify(t: Ty, u: Ty) -> Result<Substitution, Error> {
-    match (t.kind, u.kind) {
-        (TyKind::Infer(i), y) => bind(i, Ty::new(t.span, y)),
-        (x, TyKind::Infer(j)) => bind(j, Ty::new(u.span, x)),
-        (TyKind::Arrow(box l, box r), TyKind::Arrow(box t, box u)) => {
-            solve(Constraint::And(
-                box Constraint::Eq(l, t),
-                box Constraint::Eq(r, u),
-            ))
-        }
-        (TyKind::Tuple(xs), TyKind::Tuple(ys)) => {
-            let cs = xs.into_iter()
-                .zip(ys)
-                .fold(Constraint::Empty, |acc, (t, u)| Constraint::And(box acc, box Constraint::Eq(t, u)));
-            solve(cs)
-        },
-        (t, u) if t == u => Ok(HashMap::new()),
-        (x, y) => Err(Error::new(t.span.merge(u.span), format!("Failed to unify type {} with {}", x, y))),
-    }
-}
-
-/// Performs occurs check and if it passes, return mapping from inference variable to the type
-fn bind(i: u64, t: Ty) -> Result<Substitution, Error> {
-    if TyKind::Infer(i) != t.kind && t.ftv().contains(&i) { Err(Error::new(t.span, format!("Occurs check failed: {} occurs in {}", i, t))) }
-    else { Ok(map! { i => t }) }
-}
-
-
-
 /// left -> composition
 /// extend is right-biased
-fn compose(mut s: Substitution, mut t: Substitution) -> Substitution {
+pub(crate) fn compose(mut s: Substitution, mut t: Substitution) -> Substitution {
     s.values_mut().map(|ty| ty.apply(&t)).count();
     t.extend(s);
     t
@@ -60,10 +16,11 @@ mod test {
 
     use crate::map;
     use super::*;
+    use super::super::TyKind;
     use crate::parsing::Span;
 
     fn to_ty(kind: TyKind) -> Ty {
-        Ty::new(Span::single(0, 0), kind)
+        Ty::new(Span::single(0), kind)
     }
 
     #[test]
@@ -1,93 +1,770 @@
-use crate::parsing::{Expr, ExprKind, Span};
-use crate::error::Error;
-use super::{TyKind, Ty, Env, Constraint, Type, TyScheme, solve};
+use crate::parsing::{Expr, ExprKind, Span, Binder, Variant, Arm, Field, Pattern, PatternKind};
+use crate::error::{Error, ErrorCode};
+use super::{TyKind, Ty, Env, Constraint, Type, TyScheme, AdtDef, Matrix, Substitution, Unifier, compose};
+use super::{Predicate, ClassEnv, Instance, type_params};
+use super::class::{NUM, ORD, EQ};
 use crate::util::{self, Counter};
+use crate::lexing::TokenKind;
+use crate::driver::Stage;
+
+/// How the typechecker uses type annotations
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TypecheckMode {
+    /// Annotations are equated with the type inferred for the annotated expression
+    Constraints,
+    /// Annotations are pushed inward and each expression is checked against the type expected of it
+    Bidirectional,
+}
+
+/// Which variables of a let binding are generalized
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Generalization {
+    /// Variables that were created at a deeper level than the binding
+    Levels,
+    /// Variables that are not free in the environment, only kept to benchmark levels against
+    #[cfg(test)]
+    EnvScan,
+}
+
+type Tracer<'a> = &'a mut dyn FnMut(Stage, &str);
 
 pub struct Typechecker<'a> {
     env: Env<&'a str, TyScheme>,
+    adts: Env<String, AdtDef>, // Scoped like the constructors bound in `env`
     name_gen: &'a mut Counter,
+    errors: Vec<Error>, // Errors that have been recovered from
+    unifier: Unifier, // Let bindings are solved as they are inferred so the solution is shared by the whole typecheck
+    substitution: Substitution, // The solution of the last successful typecheck
+    classes: ClassEnv,
+    mode: TypecheckMode,
+    generalization: Generalization,
+    tracer: Option<Tracer<'a>>,
 }
 
 impl<'a> Typechecker<'a> {
     pub fn new(name_gen: &'a mut Counter) -> Self {
-        Self { env: Env::new(), name_gen }
+        Self { env: Env::new(), adts: Env::new(), name_gen, errors: Vec::new(), unifier: Unifier::new(), substitution: Substitution::new(), classes: ClassEnv::new(), mode: TypecheckMode::Constraints, generalization: Generalization::Levels, tracer: None }
+    }
+
+    #[cfg(test)]
+    fn with_generalization(mut self, generalization: Generalization) -> Self {
+        self.generalization = generalization;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: TypecheckMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Receives the generated constraints
+    pub fn with_tracer(mut self, tracer: Tracer<'a>) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    fn trace(&mut self, msg: impl FnOnce() -> String) {
+        if let Some(tracer) = &mut self.tracer { tracer(Stage::Typecheck, &msg()) }
     }
 
     pub fn typecheck(&mut self, expr: &'a mut Expr) -> Result<Ty, Vec<Error>> {
-        let (mut t, c) = self.infer(expr).map_err(|e| vec![e])?;
-        println!("c: {}", c);
-        let substitution = solve(c).map_err(|err| vec![err])?;
+        let span = expr.span;
+        let (mut t, c) = self.infer(expr).unwrap_or_else(|err| self.recover(err, span));
+        self.trace(|| format!("constraints: {}", c));
+        let (c, preds) = c.partition();
+        let errors = self.unifier.solve(c, self.name_gen);
+        self.errors.extend(errors);
+        let mut substitution = self.unifier.substitution();
+        // Instances can only be resolved once everything else has unified
+        if self.errors.is_empty() {
+            let mut preds = self.reduce(Self::applied(preds, &substitution));
+            let defaults = Self::defaults(&preds);
+            if !defaults.is_empty() {
+                substitution = compose(substitution, defaults);
+                preds = self.reduce(Self::applied(preds, &substitution));
+            }
+            // What is left constrains a type variable that defaulting cannot choose a type for
+            for pred in preds {
+                self.errors.push(Error::new(pred.ty.span, format!("Ambiguous type: no instance can be chosen for `{}`", pred))
+                    .with_code(ErrorCode::NoInstance)
+                    .with_help("annotate the type so the instance can be resolved".to_owned()));
+            }
+        }
+        if !self.errors.is_empty() {
+            let mut errors = std::mem::take(&mut self.errors);
+            errors.sort_by_key(|err| err.span.lo);
+            return Err(errors)
+        }
         t.apply(&substitution);
+        self.substitution = substitution;
         Normalizer::new().normalize(&mut t);
         Ok(t)
     }
 
+    /// The typechecker borrows the tree while it is alive so the solution has to be moved out before annotating the tree
+    pub(crate) fn into_substitution(self) -> Substitution {
+        self.substitution
+    }
+
+    /// Writes the solved types back into every expression and binder so the tree can be queried after typechecking
+    pub(crate) fn apply_to_tree(expr: &mut Expr, s: &Substitution) {
+        expr.ty.apply(s);
+        match &mut expr.kind {
+            ExprKind::Let { binder, bound } => {
+                binder.ty.apply(s);
+                Self::apply_to_tree(bound, s);
+            }
+            ExprKind::LetIn { binder, bound, body } => {
+                binder.ty.apply(s);
+                Self::apply_to_tree(bound, s);
+                Self::apply_to_tree(body, s);
+            }
+            ExprKind::Lambda { params, ret, body } => {
+                params.iter_mut().for_each(|binder| binder.ty.apply(s));
+                if let Some(ret) = ret { ret.apply(s) }
+                Self::apply_to_tree(body, s);
+            }
+            ExprKind::Unary { expr, .. } | ExprKind::Grouping { expr } | ExprKind::Cast { expr, .. } | ExprKind::Access { expr, .. } => Self::apply_to_tree(expr, s),
+            ExprKind::Binary { left, right, .. } => {
+                Self::apply_to_tree(left, s);
+                Self::apply_to_tree(right, s);
+            }
+            ExprKind::App { f, args } => {
+                Self::apply_to_tree(f, s);
+                args.iter_mut().for_each(|e| Self::apply_to_tree(e, s));
+            }
+            ExprKind::Block { exprs, .. } | ExprKind::Tuple { elems: exprs } => exprs.iter_mut().for_each(|e| Self::apply_to_tree(e, s)),
+            ExprKind::Record { fields } => fields.iter_mut().for_each(|field| Self::apply_to_tree(&mut field.value, s)),
+            ExprKind::Update { record, fields } => {
+                Self::apply_to_tree(record, s);
+                fields.iter_mut().for_each(|field| Self::apply_to_tree(&mut field.value, s));
+            }
+            ExprKind::If { cond, then, otherwise } => {
+                Self::apply_to_tree(cond, s);
+                Self::apply_to_tree(then, s);
+                if let Some(otherwise) = otherwise { Self::apply_to_tree(otherwise, s) }
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                Self::apply_to_tree(scrutinee, s);
+                arms.iter_mut().for_each(|arm| Self::apply_to_tree(&mut arm.body, s));
+            }
+            ExprKind::Integral { .. } | ExprKind::Floating { .. } | ExprKind::Bool { .. } | ExprKind::Id { .. } | ExprKind::Str { .. } | ExprKind::TypeDef { .. }
+                | ExprKind::Class { .. } | ExprKind::Instance { .. } => {}
+        }
+    }
+
+    /// Records the error and gives the failed expression an unconstrained type so inference can continue
+    fn recover(&mut self, err: Error, span: Span) -> (Ty, Constraint) {
+        self.errors.push(err);
+        (self.fresh_var(span), Constraint::Empty)
+    }
+
     pub fn infer(&mut self, expr: &'a mut Expr) -> Result<(Ty, Constraint), Error> {
+        let ty = expr.ty.clone();
+        let (t, c) = self.infer_expr(expr)?;
+        // Ties the type stored in the tree to the inferred type
+        Ok((t.clone(), Constraint::And(Box::new(c), Box::new(Constraint::Eq(t, ty)))))
+    }
+
+    pub(super) fn infer_expr(&mut self, expr: &'a mut Expr) -> Result<(Ty, Constraint), Error> {
         match &mut expr.kind {
             ExprKind::Id { name } => {
                 let scheme = self.env.lookup(&name.as_str())
-                    .ok_or(Error::new(expr.span, format!("Unbound variable `{}`", name)))?;
-                Ok((scheme.instantiate(self.name_gen), Constraint::Empty))
+                    .ok_or(Error::new(expr.span, format!("Unbound variable `{}`", name)).with_code(ErrorCode::UnboundVariable))?;
+                let (t, preds) = scheme.instantiate(self.name_gen);
+                // Predicates are reported at the use rather than the definition
+                let span = expr.span;
+                let preds = preds.into_iter().map(|pred| Constraint::Pred(Predicate::new(pred.class, pred.ty.with_span(span))));
+                Ok((t, Constraint::conj(preds.collect())))
             }
             ExprKind::Let { binder, bound } => {
-                self.env.push();
-                let (tbound, cbound) = self.infer(bound)?;
-                let c_tbound_eq_binder_annotation = box Constraint::Eq(tbound.clone(), binder.ty.clone());
-                let c = Constraint::And(box cbound, c_tbound_eq_binder_annotation);
-                let s = solve(c.clone())?;
-                let mut principle_ty = tbound.clone();
-                principle_ty.apply(&s);
-                let generalized = principle_ty.generalize(&self.env);
-                self.env.define(&binder.name, generalized);
+                let c = self.infer_let_group(vec![(binder, bound)], true);
                 let tret = Ty::new(expr.span, TyKind::unit()); // Let expressions always return unit;
                 Ok((tret, c))
             }
+            ExprKind::LetIn { binder, bound, body } => self.infer_let_in(binder, bound, body, None),
             ExprKind::Lambda { params, ret, body } => {
-                self.env.push();
-                let tparams = Ty::new(expr.span, TyKind::Tuple(params.iter_mut().map(|binder| {
-                    self.env.define(&binder.name, TyScheme::from(binder.ty.clone()));
-                    binder.ty.clone()
-                }).collect::<Vec<_>>()));
-
-                let (tbody, cbody) = self.infer(body)?;
-                let tlambda = Ty::new(expr.span, TyKind::Arrow(box tparams, box tbody.clone()));
-                let clambda = Constraint::Eq(tlambda.clone(), expr.ty.clone());
-                let c_ret_eq_body = Constraint::Eq(tbody, ret.clone());
-                let cs = Constraint::conj(vec![clambda, c_ret_eq_body, cbody]);
-
-                self.env.pop();
-                Ok((tlambda, cs))
+                // An unannotated return type is just the type of the body
+                let ret = ret.clone().unwrap_or_else(|| body.ty.clone());
+                self.infer_lambda(params, &ret, body, expr.span, expr.ty.clone())
             }
             ExprKind::App { f, args } => {
                 let fspan = f.span; // for borrow checker reasons
                 let (tf, cf) = self.infer(f)?;
+                // Arguments are checked against the parameters when the type of the function is already known
+                if let (TypecheckMode::Bidirectional, TyKind::Arrow(box Ty { kind: TyKind::Tuple(params), .. }, box ret)) = (self.mode, &tf.kind) {
+                    if params.len() == args.len() {
+                        let cargs = args.iter_mut().zip(params).map(|(arg, param)| self.check(arg, param)).collect::<Result<Vec<_>, _>>()?;
+                        return Ok((ret.with_span(expr.span), Constraint::And(Box::new(cf), Box::new(Constraint::conj(cargs)))))
+                    }
+                }
                 let xs = args.iter_mut().map(|e| self.infer(e)).collect::<Result<Vec<_>, _>>()?;
                 let (vargs, mut cargs) = util::split(xs);
-                let targs = box Ty::new(expr.span, TyKind::Tuple(vargs));
-                let capp = Constraint::Eq(tf, Ty::new(fspan, TyKind::Arrow(targs, box expr.ty.clone())));
+                let targs = Box::new(Ty::new(expr.span, TyKind::Tuple(vargs)));
+                // The function's type is the expected side so mismatches are labelled at its definition
+                let capp = Constraint::Eq(Ty::new(fspan, TyKind::Arrow(targs, Box::new(expr.ty.clone()))), tf);
                 cargs.extend(vec![cf, capp]);
                 let cs = Constraint::conj(cargs);
                 Ok((expr.ty.clone(), cs))
             }
-            ExprKind::Block { exprs, suppressed } => {
-                self.env.save();
-                self.env.push();
-                let xs = exprs.iter_mut().map(|e| self.infer(e)).collect::<Result<Vec<_>, _>>()?;
-                let (mut types, constraints) = util::split(xs);
-                let block_type = if *suppressed { Ty::new(expr.span, TyKind::unit()) } else { types.remove(types.len() - 1) };
-                self.env.restore();
-                Ok((block_type, Constraint::conj(constraints)))
-            }
+            ExprKind::Block { exprs, suppressed } => Ok(self.infer_block(exprs, *suppressed, expr.span, None)),
             ExprKind::Tuple { elems } => {
                 let xs = elems.iter_mut().map(|e| self.infer(e)).collect::<Result<Vec<_>, _>>()?;
                 let (types, constraints) = util::split(xs);
                 let ty = Ty::new(expr.span, TyKind::Tuple(types));
                 Ok((ty, Constraint::conj(constraints)))
             }
+            ExprKind::Record { fields } => {
+                let (fields, c) = self.infer_fields(fields)?;
+                Ok((Ty::new(expr.span, TyKind::record(fields, None)), c))
+            }
+            // The record must already have every updated field and keeps its type
+            ExprKind::Update { record, fields } => {
+                let (trecord, crecord) = self.infer(record)?;
+                let (fields, cfields) = self.infer_fields(fields)?;
+                let row = self.fresh_var(expr.span);
+                let c = Constraint::Eq(trecord.clone(), Ty::new(expr.span, TyKind::record(fields, Some(row))));
+                Ok((trecord.with_span(expr.span), Constraint::conj(vec![crecord, cfields, c])))
+            }
+            // Any record with the field can be accessed so the rest of the record is left open
+            ExprKind::Access { expr: record, field, field_span } => {
+                let (trecord, c) = self.infer(record)?;
+                let tfield = self.fresh_var(expr.span);
+                let row = self.fresh_var(*field_span);
+                let taccess = Ty::new(*field_span, TyKind::record(vec![(field.clone(), tfield.clone())], Some(row)));
+                Ok((tfield, Constraint::And(Box::new(c), Box::new(Constraint::Eq(trecord, taccess)))))
+            }
+            ExprKind::Binary { op, op_span, left, right } => {
+                let (tl, cl) = self.infer(left)?;
+                let (tr, cr) = self.infer(right)?;
+                let (t, c) = Self::infer_binary(*op, *op_span, tl, tr, expr.span)?;
+                Ok((t, Constraint::conj(vec![cl, cr, c])))
+            }
+            ExprKind::Unary { op, op_span, expr: operand } => {
+                let (t, c) = self.infer(operand)?;
+                let (tunary, cunary) = Self::infer_unary(*op, *op_span, t, expr.span)?;
+                Ok((tunary, Constraint::And(Box::new(c), Box::new(cunary))))
+            }
+            ExprKind::Cast { expr: operand, ty } => {
+                if ty.kind != TyKind::I64 && ty.kind != TyKind::F64 {
+                    return Err(Error::new(ty.span, format!("Cannot cast to type {}", ty))
+                        .with_code(ErrorCode::InvalidCast)
+                        .with_help("casts convert between `Int` and `Float`".to_owned()))
+                }
+                let span = operand.span;
+                let (t, c) = self.infer(operand)?;
+                Ok((ty.with_span(expr.span), Constraint::And(Box::new(c), Box::new(Constraint::Pred(Predicate::new(NUM, t.with_span(span)))))))
+            }
+            ExprKind::If { cond, then, otherwise } => {
+                let cond_span = cond.span;
+                let (tcond, ccond) = self.infer(cond)?;
+                let (tthen, cthen) = self.infer(then)?;
+                let c_cond_is_bool = Constraint::Eq(tcond, Ty::new(cond_span, TyKind::Bool));
+                match otherwise {
+                    Some(otherwise) => {
+                        let (totherwise, cotherwise) = self.infer(otherwise)?;
+                        let c_branches_eq = Constraint::Eq(tthen.clone(), totherwise);
+                        Ok((tthen, Constraint::conj(vec![ccond, c_cond_is_bool, cthen, cotherwise, c_branches_eq])))
+                    }
+                    // Without an else branch the value of the then branch is discarded, similar to a suppressed block
+                    None => Ok((Ty::new(expr.span, TyKind::unit()), Constraint::conj(vec![ccond, c_cond_is_bool, cthen]))),
+                }
+            }
+            ExprKind::Match { scrutinee, arms } => self.infer_match(scrutinee, arms, &expr.ty, expr.span, None),
+            ExprKind::TypeDef { name, params, variants } => {
+                self.define_adt(name, params, variants, expr.span)?;
+                Ok((Ty::new(expr.span, TyKind::unit()), Constraint::Empty))
+            }
+            ExprKind::Class { name, .. } => {
+                if self.classes.contains(name) {
+                    return Err(Error::new(expr.span, format!("Class `{}` is already declared", name)).with_code(ErrorCode::DuplicateClass))
+                }
+                self.classes.declare(name);
+                Ok((Ty::new(expr.span, TyKind::unit()), Constraint::Empty))
+            }
+            ExprKind::Instance { context, head } => {
+                self.define_instance(context, head, expr.span)?;
+                Ok((Ty::new(expr.span, TyKind::unit()), Constraint::Empty))
+            }
             ExprKind::Grouping { expr } => self.infer(expr),
-            k@ExprKind::Bool { .. } | k@ExprKind::Integral { .. } => Ok(Self::typecheck_literal(k, &expr.ty, expr.span)),
-            expr => unimplemented!("{}", expr),
+            k@ExprKind::Bool { .. } | k@ExprKind::Integral { .. } | k@ExprKind::Floating { .. } | k@ExprKind::Str { .. } => Ok(Self::typecheck_literal(k, &expr.ty, expr.span)),
+        }
+    }
+
+    /// Infers the value of each field
+    fn infer_fields(&mut self, fields: &'a mut [Field]) -> Result<(Vec<(String, Ty)>, Constraint), Error> {
+        Self::check_fields(fields)?;
+        let xs = fields.iter_mut().map(|field| {
+            let (t, c) = self.infer(&mut field.value)?;
+            Ok(((field.name.clone(), t), c))
+        }).collect::<Result<Vec<_>, Error>>()?;
+        let (fields, constraints) = util::split(xs);
+        Ok((fields, Constraint::conj(constraints)))
+    }
+
+    /// Each field of a record literal or update may only be given once
+    pub(super) fn check_fields(fields: &[Field]) -> Result<(), Error> {
+        for (i, field) in fields.iter().enumerate() {
+            if fields[..i].iter().any(|other| other.name == field.name) {
+                return Err(Error::new(field.span, format!("Field `{}` is given more than once", field.name)).with_code(ErrorCode::DuplicateField))
+            }
         }
+        Ok(())
+    }
+
+    /// Checks the expression against its annotation in bidirectional mode
+    /// Otherwise the type is inferred and the caller equates it with the annotation
+    /// `ret` is the annotated return type, or any type the body is expected to have
+    pub(super) fn infer_lambda(&mut self, params: &'a mut [Binder], ret: &Ty, body: &'a mut Expr, span: Span, ty: Ty) -> Result<(Ty, Constraint), Error> {
+        params.iter().try_for_each(|binder| self.check_annotation(&binder.ty, &[]))?;
+        self.check_annotation(ret, &[])?;
+        self.env.push();
+        let tparams = Ty::new(span, TyKind::Tuple(params.iter_mut().map(|binder| {
+            self.unifier.register(&binder.ty);
+            self.env.define(&binder.name, TyScheme::from(binder.ty.clone()));
+            binder.ty.clone()
+        }).collect::<Vec<_>>()));
+
+        let body = self.infer_annotated(body, ret);
+        self.env.pop();
+        let (tbody, cbody) = body?;
+        let tlambda = Ty::new(span, TyKind::Arrow(Box::new(tparams), Box::new(tbody.clone())));
+        let clambda = Constraint::Eq(tlambda.clone(), ty);
+        let c_ret_eq_body = Constraint::Eq(tbody, ret.clone());
+        let cs = Constraint::conj(vec![clambda, c_ret_eq_body, cbody]);
+        Ok((tlambda, cs))
+    }
+
+    fn infer_annotated(&mut self, expr: &'a mut Expr, annotation: &Ty) -> Result<(Ty, Constraint), Error> {
+        match self.mode {
+            TypecheckMode::Bidirectional => Ok((annotation.clone(), self.check(expr, annotation)?)),
+            TypecheckMode::Constraints => self.infer(expr),
+        }
+    }
+
+    fn infer_or_check(&mut self, expr: &'a mut Expr, expected: Option<&Ty>) -> Result<(Ty, Constraint), Error> {
+        match expected {
+            Some(expected) => Ok((expected.clone(), self.check(expr, expected)?)),
+            None => self.infer(expr),
+        }
+    }
+
+    /// The last expression is checked against the expected type if there is one and the block is not suppressed
+    pub(super) fn infer_block(&mut self, exprs: &'a mut [Expr], suppressed: bool, span: Span, expected: Option<&Ty>) -> (Ty, Constraint) {
+        self.env.push();
+        self.adts.push();
+        let mut types = vec![];
+        let mut constraints = vec![];
+        let mut names = HashSet::new();
+        let mut rest = exprs;
+        while !rest.is_empty() {
+            // Consecutive lambda bindings form a single group so they may be mutually recursive
+            // A lambda binding that shadows an earlier binding of the block ends the group and refers to what it shadows
+            let n = rest.iter().take_while(|e| match &e.kind {
+                ExprKind::Let { binder, bound } if bound.is_lambda() => names.insert(binder.name.clone()),
+                _ => false,
+            }).count();
+            if n == 0 {
+                let (e, tail) = std::mem::take(&mut rest).split_first_mut().unwrap();
+                rest = tail;
+                let span = e.span;
+                let expected = if rest.is_empty() && !suppressed { expected } else { None };
+                if let ExprKind::Let { binder, .. } = &e.kind { names.insert(binder.name.clone()); }
+                let (t, c) = if e.is_lambda_binding() {
+                    match &mut e.kind {
+                        ExprKind::Let { binder, bound } => (Ty::new(span, TyKind::unit()), self.infer_let_group(vec![(binder, bound)], false)),
+                        _ => unreachable!(),
+                    }
+                } else {
+                    self.infer_or_check(e, expected).unwrap_or_else(|err| self.recover(err, span))
+                };
+                types.push(t);
+                constraints.push(c);
+                continue
+            }
+            let (group, tail) = std::mem::take(&mut rest).split_at_mut(n);
+            rest = tail;
+            let spans = group.iter().map(|e| e.span).collect::<Vec<_>>();
+            let bindings = group.iter_mut().map(|e| match &mut e.kind {
+                ExprKind::Let { binder, bound } => (binder, bound),
+                _ => unreachable!(),
+            }).collect();
+            constraints.push(self.infer_let_group(bindings, true));
+            types.extend(spans.into_iter().map(|span| Ty::new(span, TyKind::unit())));
+        }
+        let block_type = if suppressed { Ty::new(span, TyKind::unit()) } else { types.remove(types.len() - 1) };
+        self.adts.pop();
+        self.env.pop();
+        (block_type, Constraint::conj(constraints))
+    }
+
+    /// The binding is generalized before the body is inferred, or checked against the expected type if there is one
+    pub(super) fn infer_let_in(&mut self, binder: &'a mut Binder, bound: &'a mut Box<Expr>, body: &'a mut Expr, expected: Option<&Ty>) -> Result<(Ty, Constraint), Error> {
+        self.env.push();
+        let c = self.infer_let_group(vec![(binder, bound)], true);
+        let body = self.infer_or_check(body, expected);
+        self.env.pop();
+        let (tbody, cbody) = body?;
+        Ok((tbody, Constraint::And(Box::new(c), Box::new(cbody))))
+    }
+
+    /// `ty` is the type of the match expression and the arms are checked against the expected type if there is one
+    pub(super) fn infer_match(&mut self, scrutinee: &'a mut Expr, arms: &'a mut [Arm], ty: &Ty, span: Span, expected: Option<&Ty>) -> Result<(Ty, Constraint), Error> {
+        let (tscrutinee, cscrutinee) = self.infer(scrutinee)?;
+        let mut constraints = vec![cscrutinee];
+        let mut pats = vec![];
+        for Arm { pat, body, .. } in arms.iter_mut() {
+            let pat: &'a Pattern = pat;
+            self.env.push();
+            let arm = match self.infer_pattern(pat, &mut vec![]) {
+                Ok(tpat) => self.infer_or_check(body, expected).map(|tbody| (tpat, tbody)),
+                Err(err) => Err(err),
+            };
+            self.env.pop();
+            let ((tpat, cpat), (tbody, cbody)) = arm?;
+            constraints.extend(vec![cpat, Constraint::Eq(tpat, tscrutinee.with_span(pat.span)), cbody, Constraint::Eq(tbody, ty.clone())]);
+            pats.push(pat);
+        }
+        let errors = self.check_exhaustiveness(&pats, span);
+        self.errors.extend(errors);
+        Ok((ty.clone(), Constraint::conj(constraints)))
+    }
+
+    /// Infers a group of mutually recursive let bindings, which are monomorphic within the group
+    /// The group is solved on its own and generalized over the variables that did not escape to an enclosing level
+    /// Lambda bindings can only refer to themselves and the rest of the group if it is `recursive`
+    fn infer_let_group(&mut self, bindings: Vec<(&'a mut Binder, &'a mut Box<Expr>)>, recursive: bool) -> Constraint {
+        // The monomorphic binders live in their own scope so they are not in the environment the group is generalized against
+        self.env.push();
+        self.unifier.enter_level();
+        let (binders, bounds): (Vec<&'a Binder>, Vec<_>) = bindings.into_iter().map(|(binder, bound)| (&*binder, bound)).unzip();
+        for binder in &binders {
+            if let Err(err) = self.check_annotation(&binder.ty, &[]) { self.errors.push(err) }
+            self.unifier.register(&binder.ty);
+        }
+        for (binder, bound) in binders.iter().zip(&bounds) {
+            if recursive && bound.is_lambda() { self.env.define(&binder.name, TyScheme::from(&binder.ty)) }
+        }
+
+        let mut constraints = vec![];
+        for (binder, bound) in binders.iter().zip(bounds) {
+            let span = bound.span;
+            let (tbound, cbound) = self.infer_annotated(bound, &binder.ty).unwrap_or_else(|err| self.recover(err, span));
+            constraints.push(cbound);
+            constraints.push(Constraint::Eq(tbound, binder.ty.clone()));
+        }
+
+        // The group is solved here so its constraint never reaches the top level trace
+        let c = Constraint::conj(constraints);
+        self.trace(|| format!("constraints of {}: {}", binders.iter().map(|binder| format!("`{}`", binder.name)).collect::<Vec<_>>().join(", "), c));
+        let (c, preds) = c.partition();
+        let errors = self.unifier.solve(c, self.name_gen);
+        let preds = preds.into_iter().map(|pred| Predicate::new(pred.class, self.unifier.apply(&pred.ty))).collect();
+        let mut preds = if errors.is_empty() { self.reduce(preds) } else { preds };
+        self.errors.extend(errors);
+        self.unifier.exit_level();
+        self.env.pop();
+        let env_vars: Option<HashSet<u64>> = match self.generalization {
+            Generalization::Levels => None,
+            #[cfg(test)]
+            Generalization::EnvScan => Some(self.env.ftv().into_iter().flat_map(|i| self.unifier.apply(&TyKind::Infer(i).to_ty()).ftv()).collect()),
+        };
+        let mut qualified = vec![];
+        for binder in binders {
+            let principle_ty = self.unifier.apply(&binder.ty);
+            let unifier = &self.unifier;
+            let generalized = principle_ty.generalize(|i| match &env_vars {
+                Some(env_vars) => !env_vars.contains(&i),
+                None => unifier.is_generalizable(i),
+            }, &preds);
+            qualified.extend(generalized.preds().iter().cloned());
+            self.env.define(&binder.name, generalized);
+        }
+        // Predicates on variables that were not generalized constrain the enclosing expression instead
+        preds.retain(|pred| !qualified.contains(pred));
+        Constraint::conj(preds.into_iter().map(Constraint::Pred).collect())
+    }
+
+    /// Binds each variable of the pattern monomorphically in the current scope
+    /// `bound` accumulates the names bound so far in the pattern so duplicates can be reported
+    fn infer_pattern(&mut self, pat: &'a Pattern, bound: &mut Vec<&'a str>) -> Result<(Ty, Constraint), Error> {
+        match &pat.kind {
+            PatternKind::Wildcard => Ok((self.fresh_var(pat.span), Constraint::Empty)),
+            PatternKind::Binding { name } => {
+                if bound.contains(&name.as_str()) {
+                    return Err(Error::new(pat.span, format!("Identifier `{}` is bound more than once in the same pattern", name)).with_code(ErrorCode::DuplicateBinding))
+                }
+                bound.push(name);
+                let ty = self.fresh_var(pat.span);
+                self.unifier.register(&ty);
+                self.env.define(name, TyScheme::from(&ty));
+                Ok((ty, Constraint::Empty))
+            }
+            PatternKind::Integral { .. } => Ok((Ty::new(pat.span, TyKind::I64), Constraint::Empty)),
+            PatternKind::Bool { .. }     => Ok((Ty::new(pat.span, TyKind::Bool), Constraint::Empty)),
+            PatternKind::Str { .. }      => Ok((Ty::new(pat.span, TyKind::Str), Constraint::Empty)),
+            PatternKind::Tuple { elems } => {
+                let xs = elems.iter().map(|p| self.infer_pattern(p, bound)).collect::<Result<Vec<_>, _>>()?;
+                let (types, constraints) = util::split(xs);
+                Ok((Ty::new(pat.span, TyKind::Tuple(types)), Constraint::conj(constraints)))
+            }
+            PatternKind::Constructor { name, args } => {
+                let (tctor, _) = self.env.lookup(&name.as_str())
+                    .ok_or(Error::new(pat.span, format!("Unknown constructor `{}`", name)).with_code(ErrorCode::UnknownConstructor))?
+                    .instantiate(self.name_gen);
+                let (fields, tadt) = match tctor.kind {
+                    TyKind::Arrow(box Ty { kind: TyKind::Tuple(fields), .. }, box tadt) => (fields, tadt),
+                    kind => (vec![], Ty::new(pat.span, kind)),
+                };
+                if fields.len() != args.len() {
+                    return Err(Error::new(pat.span, format!("Constructor `{}` expects {} fields, found {}", name, fields.len(), args.len())).with_code(ErrorCode::ConstructorArity))
+                }
+                let mut constraints = vec![];
+                for (field, arg) in fields.into_iter().zip(args) {
+                    let (targ, carg) = self.infer_pattern(arg, bound)?;
+                    constraints.extend(vec![carg, Constraint::Eq(targ, field.with_span(arg.span))]);
+                }
+                Ok((tadt.with_span(pat.span), Constraint::conj(constraints)))
+            }
+        }
+    }
+
+    /// Reports every unreachable arm along with the earlier arms that cover it, then any value no arm matches
+    fn check_exhaustiveness(&self, pats: &[&Pattern], span: Span) -> Vec<Error> {
+        let adt_of = |ctor: &str| self.adt_of(ctor);
+        let matrix = Matrix::new(&self.adts, &adt_of);
+        let mut errors = vec![];
+        for (i, pat) in pats.iter().enumerate() {
+            if matrix.is_useful(&pats[..i], pat) { continue }
+            // Drops the earlier arms the pattern stays covered without, leaving a minimal set of arms that cover it
+            let mut covering = pats[..i].to_vec();
+            let mut j = 0;
+            while j < covering.len() {
+                let without = covering.iter().enumerate().filter(|&(k, _)| k != j).map(|(_, p)| *p).collect::<Vec<_>>();
+                if matrix.is_useful(&without, pat) { j += 1 } else { covering = without }
+            }
+            let err = Error::new(pat.span, format!("Unreachable pattern `{}`", pat))
+                .with_code(ErrorCode::UnreachablePattern)
+                .with_help("every value matched by this arm is matched by an earlier arm".to_owned());
+            errors.push(covering.iter().fold(err, |err, p| err.with_label(p.span, "matched here first".to_owned())));
+        }
+        let missing = match matrix.missing(pats) {
+            Some(witness) => {
+                let span = match pats.split_first() {
+                    Some((p, ps)) => ps.iter().fold(p.span, |acc, p| acc.merge(p.span)),
+                    None => span,
+                };
+                let witness = witness.to_string();
+                let help = if witness == "_" { "add a wildcard `_` arm".to_owned() }
+                    else { format!("add an arm for `{}` or a wildcard `_` arm", witness) };
+                Some(Error::new(span, format!("Non-exhaustive patterns: `{}` not covered", witness))
+                    .with_code(ErrorCode::NonExhaustive)
+                    .with_help(help))
+            }
+            None => None,
+        };
+        errors.extend(missing);
+        errors
+    }
+
+    /// Applies the substitution to every predicate
+    fn applied(mut preds: Vec<Predicate>, s: &Substitution) -> Vec<Predicate> {
+        preds.iter_mut().for_each(|pred| pred.apply(s));
+        preds
+    }
+
+    /// Reduces the already solved predicates through the instances
+    /// Predicates without an instance are reported and the rest are left on unsolved types
+    fn reduce(&mut self, preds: Vec<Predicate>) -> Vec<Predicate> {
+        let mut reduced = vec![];
+        for pred in preds {
+            let span = pred.ty.span;
+            let required_by = pred.to_string();
+            match self.classes.reduce(pred) {
+                Ok(preds) => reduced.extend(preds),
+                Err(missing) => {
+                    let err = Error::new(span, format!("No instance for `{}`", missing)).with_code(ErrorCode::NoInstance);
+                    let missing = missing.to_string();
+                    self.errors.push(if missing == required_by { err } else { err.with_help(format!("`{}` requires `{}`", required_by, missing)) })
+                }
+            }
+        }
+        reduced
+    }
+
+    /// Variables only known to be numeric or ordered default to `Int` as numeric literals are not overloaded
+    fn defaults(preds: &[Predicate]) -> Substitution {
+        preds.iter().filter_map(|pred| match pred.ty.kind {
+            TyKind::Infer(i) if pred.class == NUM || pred.class == ORD => Some((i, Ty::new(pred.ty.span, TyKind::I64))),
+            _ => None,
+        }).collect()
+    }
+
+    fn check_class(&self, pred: &Predicate) -> Result<(), Error> {
+        if self.classes.contains(&pred.class) { Ok(()) }
+        else { Err(Error::new(pred.ty.span, format!("Unknown class `{}`", pred.class)).with_code(ErrorCode::UnknownClass).with_help(format!("declare it with `class {} a`", pred.class))) }
+    }
+
+    /// Instances are declared for a type constructor applied to type variables
+    /// The context may only constrain those variables and the instance may not overlap another of the same class
+    fn define_instance(&mut self, context: &[Predicate], head: &Predicate, span: Span) -> Result<(), Error> {
+        self.check_class(head)?;
+        if let TyKind::TyVar(_) = head.ty.kind {
+            return Err(Error::new(head.ty.span, format!("Instance `{}` must be for a type constructor", head)).with_code(ErrorCode::InvalidInstance))
+        }
+        let params = type_params(&head.ty);
+        self.check_annotation(&head.ty, &params)?;
+        for pred in context {
+            self.check_class(pred)?;
+            self.check_annotation(&pred.ty, &params)?;
+        }
+        if head.class == NUM || head.class == ORD {
+            return Err(Error::new(span, format!("Instances of the builtin class `{}` cannot be declared", head.class))
+                .with_code(ErrorCode::InvalidInstance)
+                .with_help("arithmetic and comparison operators are only implemented for `Int` and `Float`".to_owned()))
+        }
+        if let Some(existing) = self.classes.overlapping(head) {
+            let err = Error::new(span, format!("Instance `{}` overlaps an existing instance", head)).with_code(ErrorCode::DuplicateInstance);
+            return Err(match existing.span {
+                Some(existing) => err.with_label(existing, "previous instance declared here".to_owned()),
+                None => err.with_help(format!("`{}` is a builtin or derived instance", existing.head)),
+            })
+        }
+        self.classes.add_instance(&head.class, Instance::new(context.to_vec(), head.ty.clone(), Some(span)));
+        Ok(())
+    }
+
+    /// Constructors are resolved to the type they construct, which is the type of the value they match
+    fn adt_of(&self, ctor: &str) -> Option<String> {
+        let ty = self.env.lookup(ctor)?.ty();
+        let tadt = match &ty.kind { TyKind::Arrow(_, tadt) => tadt, _ => ty };
+        match &tadt.kind {
+            TyKind::Adt(name, _) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    fn fresh_var(&mut self, span: Span) -> Ty {
+        Ty::new(span, TyKind::Infer(self.name_gen.next()))
+    }
+
+    /// Registers the data type and binds each constructor in the environment
+    /// Nullary constructors have the type of the adt itself, others are functions from their fields to the adt
+    fn define_adt(&mut self, name: &'a str, params: &'a [String], variants: &'a [Variant], span: Span) -> Result<(), Error> {
+        if let Some(existing) = self.adts.lookup(name) {
+            return Err(Error::new(span, format!("Type `{}` is already declared", name))
+                .with_code(ErrorCode::DuplicateType)
+                .with_label(existing.span, "previous declaration here".to_owned()))
+        }
+        for variant in variants {
+            let other = self.adt_of(&variant.name).and_then(|other| self.adts.lookup(&other).map(|adt| (other, adt)));
+            if let Some((other, adt)) = other {
+                return Err(Error::new(variant.span, format!("Constructor `{}` is already defined by type `{}`", variant.name, other))
+                    .with_code(ErrorCode::DuplicateConstructor)
+                    .with_label(adt.span, format!("`{}` declared here", other)))
+            }
+        }
+        let ctors = variants.iter().map(|variant| (variant.name.clone(), variant.fields.len())).collect();
+        // Register before checking the fields to allow recursive data types
+        self.adts.define(name.to_owned(), AdtDef::new(params.len(), ctors, span));
+
+        let vars = params.iter().map(|param| (param.as_str(), self.name_gen.next())).collect::<HashMap<_, _>>();
+        let forall = vars.values().copied().collect::<HashSet<_>>();
+        let args = params.iter().map(|param| Ty::new(span, TyKind::Infer(vars[param.as_str()]))).collect();
+        let tadt = Ty::new(span, TyKind::Adt(name.to_owned(), args));
+
+        for (i, variant) in variants.iter().enumerate() {
+            if let Some(prev) = variants[..i].iter().find(|v| v.name == variant.name) {
+                return Err(Error::new(variant.span, format!("Duplicate constructor `{}` in type `{}`", variant.name, name))
+                    .with_code(ErrorCode::DuplicateConstructor)
+                    .with_label(prev.span, "first defined here".to_owned()))
+            }
+            let mut fields = variant.fields.clone();
+            for field in &mut fields {
+                self.check_annotation(field, params)?;
+                field.instantiate_params(&vars);
+            }
+            let tctor = if fields.is_empty() { tadt.clone() } else {
+                Ty::new(variant.span, TyKind::Arrow(Box::new(Ty::new(variant.span, TyKind::Tuple(fields))), Box::new(tadt.clone())))
+            };
+            self.env.define(&variant.name, TyScheme::new(tctor, forall.clone()));
+        }
+
+        // Equality is derived from equality of the fields where the type itself is assumed to have it
+        let head = Ty::new(span, TyKind::Adt(name.to_owned(), params.iter().map(|param| Ty::new(span, TyKind::TyVar(param.clone()))).collect()));
+        let mut context = vec![];
+        for field in variants.iter().flat_map(|variant| &variant.fields) {
+            let pred = Predicate::new(EQ, field.clone());
+            if *field != head && !context.contains(&pred) { context.push(pred) }
+        }
+        self.classes.derive(EQ, Instance::new(context, head, None));
+        Ok(())
+    }
+
+    /// Checks that every type named in an annotation has been declared with the correct number of arguments
+    /// `params` are the type variables in scope
+    fn check_annotation(&self, ty: &Ty, params: &[String]) -> Result<(), Error> {
+        match &ty.kind {
+            TyKind::Adt(name, args) => {
+                let adt = self.adts.lookup(name).ok_or_else(|| Error::new(ty.span, format!("Unknown type `{}`", name))
+                    .with_code(ErrorCode::UnknownType)
+                    .with_help(format!("declare it with `type {} = ...`", name)))?;
+                if adt.arity != args.len() {
+                    return Err(Error::new(ty.span, format!("Type `{}` expects {} type arguments, found {}", name, adt.arity, args.len())).with_code(ErrorCode::TypeArity))
+                }
+                args.iter().try_for_each(|t| self.check_annotation(t, params))
+            }
+            TyKind::TyVar(name) => if params.contains(name) { Ok(()) }
+                else { Err(Error::new(ty.span, format!("Unbound type variable `{}`", name)).with_code(ErrorCode::UnboundTypeVariable)) },
+            TyKind::Tuple(xs) => xs.iter().try_for_each(|t| self.check_annotation(t, params)),
+            TyKind::Record(fields, _) => {
+                // Fields are sorted so a repeated field follows the first
+                if let Some(pair) = fields.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                    return Err(Error::new(ty.span, format!("Field `{}` is given more than once", pair[0].0)).with_code(ErrorCode::DuplicateField))
+                }
+                fields.iter().try_for_each(|(_, t)| self.check_annotation(t, params))
+            }
+            TyKind::Arrow(l, r) => self.check_annotation(l, params).and_then(|_| self.check_annotation(r, params)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The operand types are respanned to the operator so unification failures are reported there
+    /// Arithmetic, comparison and equality are overloaded through the `Num`, `Ord` and `Eq` classes
+    fn infer_binary(op: TokenKind, op_span: Span, tl: Ty, tr: Ty, span: Span) -> Result<(Ty, Constraint), Error> {
+        let (tl, tr) = (tl.with_span(op_span), tr.with_span(op_span));
+        match op {
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Star | TokenKind::Slash | TokenKind::DStar => {
+                let c = Constraint::conj(vec![Constraint::Eq(tl.clone(), tr), Constraint::Pred(Predicate::new(NUM, tl.clone()))]);
+                Ok((tl.with_span(span), c))
+            }
+            TokenKind::LT | TokenKind::LTE | TokenKind::GT | TokenKind::GTE => {
+                let c = Constraint::conj(vec![Constraint::Eq(tl.clone(), tr), Constraint::Pred(Predicate::new(ORD, tl))]);
+                Ok((Ty::new(span, TyKind::Bool), c))
+            }
+            TokenKind::DPlus => {
+                let string = Ty::new(op_span, TyKind::Str);
+                let c = Constraint::conj(vec![Constraint::Eq(tl, string.clone()), Constraint::Eq(tr, string)]);
+                Ok((Ty::new(span, TyKind::Str), c))
+            }
+            TokenKind::DEqual | TokenKind::BangEqual => {
+                let c = Constraint::conj(vec![Constraint::Eq(tl.clone(), tr), Constraint::Pred(Predicate::new(EQ, tl))]);
+                Ok((Ty::new(span, TyKind::Bool), c))
+            }
+            _ => Err(Error::new(op_span, format!("Unknown binary operator `{}`", op)).with_code(ErrorCode::UnknownOperator)),
+        }
+    }
+
+    fn infer_unary(op: TokenKind, op_span: Span, t: Ty, span: Span) -> Result<(Ty, Constraint), Error> {
+        let t = t.with_span(op_span);
+        let operand_kind = match op {
+            TokenKind::Minus | TokenKind::Plus => return Ok((t.with_span(span), Constraint::Pred(Predicate::new(NUM, t)))),
+            TokenKind::Tilde => TyKind::I64,
+            TokenKind::Bang => TyKind::Bool,
+            _ => return Err(Error::new(op_span, format!("Unknown unary operator `{}`", op)).with_code(ErrorCode::UnknownOperator)),
+        };
+        let c = Constraint::Eq(t, Ty::new(op_span, operand_kind.clone()));
+        Ok((Ty::new(span, operand_kind), c))
     }
 
     fn typecheck_literal(exprkind: &ExprKind, ty: &Ty, span: Span) -> (Ty, Constraint) {
@@ -98,18 +775,20 @@ impl<'a> Typechecker<'a> {
     fn type_of_literal_expr(exprkind: &ExprKind) -> TyKind {
         match exprkind {
             ExprKind::Integral { .. } => TyKind::I64,
+            ExprKind::Floating { .. } => TyKind::F64,
             ExprKind::Bool { .. }     => TyKind::Bool,
+            ExprKind::Str { .. }      => TyKind::Str,
             _ => panic!("{} is not a literal", exprkind)
         }
     }
 
 }
 
-use variable_gen::Generator;
-use std::collections::HashMap;
+use crate::util::Generator;
+use std::collections::{HashMap, HashSet};
 
 /// simplfiies type names
-struct Normalizer {
+pub(crate) struct Normalizer {
     name_gen: Generator,
     names: HashMap<u64, String>,
 }
@@ -134,7 +813,11 @@ impl Normalizer {
                 self.normalize(l);
                 self.normalize(r);
             }
-            TyKind::Tuple(ts) => ts.iter_mut().for_each(|t| self.normalize(t)),
+            TyKind::Tuple(ts) | TyKind::Adt(_, ts) => ts.iter_mut().for_each(|t| self.normalize(t)),
+            TyKind::Record(fields, row) => {
+                fields.iter_mut().for_each(|(_, t)| self.normalize(t));
+                if let Some(row) = row { self.normalize(row) }
+            }
             _ => {}
         }
     }
@@ -142,14 +825,304 @@ impl Normalizer {
 
 #[cfg(test)]
 mod test {
+    extern crate test;
+
     use super::*;
     use crate::arrow;
+    use test::Bencher;
 
     macro_rules! typecheck { ($src:expr) => { { crate::generate_ast($src).unwrap().0 } } }
+    macro_rules! typecheck_err { ($src:expr) => { { crate::generate_ast($src).unwrap_err().remove(0) } } }
 
     #[test] fn typeof_int() { assert_eq!(typecheck!("5"), TyKind::I64.to_ty()) }
     #[test] fn typeof_bool() { assert_eq!(typecheck!("false"), TyKind::Bool.to_ty()) }
 
+    #[test] fn typeof_string() { assert_eq!(typecheck!("\"hello\""), TyKind::Str.to_ty()) }
+    #[test] fn typeof_concat() { assert_eq!(typecheck!("\"hello\" ++ \" \" ++ \"world\""), TyKind::Str.to_ty()) }
+    #[test] fn typeof_string_equality() { assert_eq!(typecheck!("\"a\" != \"b\""), TyKind::Bool.to_ty()) }
+    #[test] fn concat_non_string_fails() { assert!(crate::generate_ast("\"a\" ++ 1").is_err()) }
+
+    #[test]
+    fn typeof_string_annotation() {
+        assert_eq!(
+            typecheck!("fn s: String => s ++ \"!\""),
+            arrow!(TyKind::Str.to_ty().singleton() => TyKind::Str.to_ty())
+        )
+    }
+
+    #[test] fn typeof_arithmetic() { assert_eq!(typecheck!("1 + 2 * 3 ** 2 - 4 / 2"), TyKind::I64.to_ty()) }
+    #[test] fn typeof_comparison() { assert_eq!(typecheck!("1 + 2 <= 4"), TyKind::Bool.to_ty()) }
+    #[test] fn typeof_equality() { assert_eq!(typecheck!("(1, false) == (2, true)"), TyKind::Bool.to_ty()) }
+    #[test] fn typeof_negation() { assert_eq!(typecheck!("!false"), TyKind::Bool.to_ty()) }
+    #[test] fn typeof_unary_minus() { assert_eq!(typecheck!("-5"), TyKind::I64.to_ty()) }
+
+    #[test] fn typeof_float() { assert_eq!(typecheck!("1.5e3"), TyKind::F64.to_ty()) }
+    #[test] fn typeof_float_arithmetic() { assert_eq!(typecheck!("-1.5 * 2.0 ** 3.0 / 4.0"), TyKind::F64.to_ty()) }
+    #[test] fn typeof_cast() { assert_eq!(typecheck!("(1 as Float + 0.5, 2.5 as Int + 1)"), TyKind::Tuple(vec![TyKind::F64.to_ty(), TyKind::I64.to_ty()]).to_ty()) }
+    #[test] fn mixed_arithmetic_fails() { assert!(crate::generate_ast("1 + 2.0").is_err()) }
+
+    #[test]
+    fn typeof_float_annotation() {
+        assert_eq!(
+            typecheck!("fn (x: Float, y) => x * y"),
+            arrow!(TyKind::Tuple(vec![TyKind::F64.to_ty(), TyKind::F64.to_ty()]).to_ty() => TyKind::F64.to_ty())
+        )
+    }
+
+    #[test]
+    fn unconstrained_arithmetic_defaults_to_int() {
+        assert_eq!(typecheck!("fn (x, y) => x < y"), arrow!(TyKind::Tuple(vec![TyKind::I64.to_ty(), TyKind::I64.to_ty()]).to_ty() => TyKind::Bool.to_ty()))
+    }
+
+    #[test]
+    fn arithmetic_bindings_are_overloaded() {
+        assert_eq!(typecheck!("{ let square = fn x => x * x; (square(1.5), square(2)) }"), TyKind::Tuple(vec![TyKind::F64.to_ty(), TyKind::I64.to_ty()]).to_ty());
+        assert_eq!(typecheck!("{ let max = fn (x, y) => if x < y then y else x; max(1.5, 2.0) }"), TyKind::F64.to_ty())
+    }
+
+    #[test]
+    fn arithmetic_on_non_numeric_fails() {
+        let err = typecheck_err!("true + false");
+        assert_eq!(err.code, Some(ErrorCode::NoInstance));
+        assert_eq!(err.msg, "No instance for `Num bool`")
+    }
+
+    #[test]
+    fn unresolved_predicate_is_ambiguous() {
+        // Numeric variables default to `Int` but nothing chooses an `Eq` instance
+        assert_eq!(typecheck!("fn x => x + x"), arrow!(TyKind::I64.to_ty().singleton() => TyKind::I64.to_ty()));
+        let err = typecheck_err!("fn x => x == x");
+        assert_eq!((err.code, err.span.lo), (Some(ErrorCode::NoInstance), 10));
+        assert!(err.msg.starts_with("Ambiguous type"))
+    }
+
+    #[test]
+    fn qualified_binding_reports_at_use() {
+        let err = typecheck_err!("{ let square = fn x => x * x; square(\"a\") }");
+        assert_eq!(err.msg, "No instance for `Num str`");
+        assert_eq!(err.span.lo, 30)
+    }
+
+    #[test]
+    fn equality_requires_eq_instance() {
+        assert_eq!(typecheck!("{ type Option<a> = None | Some(a); (Some((1, 2.5)) == None, (true, \"a\") != (false, \"b\")) }"), TyKind::Tuple(vec![TyKind::Bool.to_ty(), TyKind::Bool.to_ty()]).to_ty());
+        let err = typecheck_err!("{ type Option<a> = None | Some(a); let f = fn x: Int => x; Some((1, f)) == None }");
+        assert_eq!(err.code, Some(ErrorCode::NoInstance));
+        assert_eq!(err.help.unwrap(), "`Eq Option<(i64, (i64) -> i64)>` requires `Eq (i64) -> i64`")
+    }
+
+    #[test]
+    fn recursive_data_types_derive_eq() {
+        assert_eq!(typecheck!("{ type List<a> = Nil | Cons(a, List<a>); Cons(1, Nil) == Nil }"), TyKind::Bool.to_ty())
+    }
+
+    #[test]
+    fn user_classes_and_instances() {
+        assert_eq!(typecheck!("{ class Show a; instance Show Int; instance Show a => Show (a, a); type Money = Money(Int); instance Show Money; Money(1) == Money(2) }"), TyKind::Bool.to_ty());
+        assert_eq!(typecheck_err!("{ type Money = Money(Int); instance Num Money; Money(1) + Money(2) }").code, Some(ErrorCode::InvalidInstance));
+        assert_eq!(typecheck_err!("{ type Money = Money(Int); instance Ord Money; Money(1) < Money(2) }").code, Some(ErrorCode::InvalidInstance));
+        assert_eq!(typecheck_err!("class Eq a").code, Some(ErrorCode::DuplicateClass));
+        assert_eq!(typecheck_err!("instance Show Int").code, Some(ErrorCode::UnknownClass));
+        assert_eq!(typecheck_err!("instance Num a").code, Some(ErrorCode::InvalidInstance));
+        assert_eq!(typecheck_err!("instance Eq Float").code, Some(ErrorCode::DuplicateInstance));
+        assert_eq!(typecheck_err!("instance Num b => Num Int").code, Some(ErrorCode::UnboundTypeVariable));
+        let err = typecheck_err!("{ class C a; instance C (a, Int); instance C (Bool, b) }");
+        assert_eq!(err.labels[0].span.lo, 13)
+    }
+
+    #[test] fn cast_to_non_numeric_fails() { assert_eq!(typecheck_err!("1 as Bool").code, Some(ErrorCode::InvalidCast)) }
+
+    #[test]
+    fn typeof_operator_on_param() {
+        assert_eq!(
+            typecheck!("fn x => x + 1"),
+            arrow!(TyKind::I64.to_ty().singleton() => TyKind::I64.to_ty())
+        )
+    }
+
+    #[test]
+    fn operator_error_points_at_operator() {
+        let errors = crate::generate_ast("1 + false").unwrap_err();
+        assert_eq!((errors[0].span.lo, errors[0].span.hi), (2, 3))
+    }
+
+    #[test]
+    fn equality_on_different_types_fails() { assert!(crate::generate_ast("1 == false").is_err()) }
+
+    #[test]
+    fn typeof_recursive_fn() {
+        let t = typecheck!("{ let f = fn x => f(x); f }");
+        // (a) -> b
+        let expected = arrow!(TyKind::TyVar("a".to_owned()).to_ty().singleton() => TyKind::TyVar("b".to_owned()).to_ty());
+        assert_eq!(t, expected)
+    }
+
+    #[test]
+    fn typeof_mutually_recursive_fns() {
+        let t = typecheck!("{ let even = fn n => odd(n - 1); let odd = fn n => even(n) == true; even }");
+        assert_eq!(t, arrow!(TyKind::I64.to_ty().singleton() => TyKind::Bool.to_ty()))
+    }
+
+    #[test]
+    fn shadowing_lambda_refers_to_what_it_shadows() {
+        let t = typecheck!("{ let f = fn x => x + 1; let f = fn y => f(y) * 2; f }");
+        assert_eq!(t, arrow!(TyKind::I64.to_ty().singleton() => TyKind::I64.to_ty()));
+        assert_eq!(typecheck!("{ let f = fn x => x + 1; let f = fn y => f(y) * 2; f(3) }"), TyKind::I64.to_ty())
+    }
+
+    #[test]
+    fn recursive_fn_is_generalized_after_group() {
+        let t = typecheck!("{ let f = fn x => f(x); (f(1), f(false)) }");
+        let expected = TyKind::Tuple(vec![
+            TyKind::TyVar("a".to_owned()).to_ty(),
+            TyKind::TyVar("b".to_owned()).to_ty(),
+        ]).to_ty();
+        assert_eq!(t, expected)
+    }
+
+    #[test]
+    fn recursion_is_monomorphic() { assert!(crate::generate_ast("{ let f = fn x => { f(1); f(false) }; f }").is_err()) }
+
+    #[test]
+    fn non_lambda_let_is_not_recursive() { assert!(crate::generate_ast("{ let x = x; x }").is_err()) }
+
+    #[test] fn typeof_if() { assert_eq!(typecheck!("if 1 < 2 then \"yes\" else \"no\""), TyKind::Str.to_ty()) }
+    #[test] fn typeof_if_without_else() { assert_eq!(typecheck!("if true then 5"), TyKind::unit().to_ty()) }
+    #[test] fn if_branch_mismatch_fails() { assert!(crate::generate_ast("if true then 5 else false").is_err()) }
+    #[test] fn if_non_bool_condition_fails() { assert!(crate::generate_ast("if 1 then 5 else 6").is_err()) }
+
+    #[test]
+    fn typeof_recursive_factorial() {
+        let t = typecheck!("{ let fact = fn n => if n <= 1 then 1 else n * fact(n - 1); fact }");
+        assert_eq!(t, arrow!(TyKind::I64.to_ty().singleton() => TyKind::I64.to_ty()))
+    }
+
+    fn adt(name: &str, args: Vec<Ty>) -> Ty { TyKind::Adt(name.to_owned(), args).to_ty() }
+
+    #[test]
+    fn typeof_constructors() {
+        let t = typecheck!("{ type Option<a> = None | Some(a); (Some(5), None) }");
+        let expected = TyKind::Tuple(vec![
+            adt("Option", vec![TyKind::I64.to_ty()]),
+            adt("Option", vec![TyKind::TyVar("a".to_owned()).to_ty()]),
+        ]).to_ty();
+        assert_eq!(t, expected)
+    }
+
+    #[test]
+    fn typeof_recursive_adt() {
+        let t = typecheck!("{ type List<a> = Nil | Cons(a, List<a>); Cons(1, Cons(2, Nil)) }");
+        assert_eq!(t, adt("List", vec![TyKind::I64.to_ty()]))
+    }
+
+    #[test]
+    fn typeof_adt_annotation() {
+        let t = typecheck!("{ type Option<a> = None | Some(a); fn x: Option<Int> => x }");
+        let topt = adt("Option", vec![TyKind::I64.to_ty()]);
+        assert_eq!(t, arrow!(topt.clone().singleton() => topt))
+    }
+
+    #[test]
+    fn adt_argument_mismatch_fails() {
+        assert!(crate::generate_ast("{ type List<a> = Nil | Cons(a, List<a>); Cons(1, Cons(false, Nil)) }").is_err())
+    }
+
+    #[test] fn unknown_type_fails() { assert!(crate::generate_ast("fn x: Foo => x").is_err()) }
+    #[test] fn adt_arity_mismatch_fails() { assert!(crate::generate_ast("{ type Option<a> = None | Some(a); fn x: Option => x }").is_err()) }
+
+    #[test]
+    fn duplicate_types_fail() {
+        assert_eq!(typecheck_err!("{ type A = X; type A = Y; X }").code, Some(ErrorCode::DuplicateType));
+        let err = typecheck_err!("{ type A = X | Y; type B = Y | Z; X }");
+        assert_eq!((err.code, err.labels[0].span.lo), (Some(ErrorCode::DuplicateConstructor), 2));
+    }
+
+    #[test]
+    fn types_are_scoped_to_their_block() {
+        assert_eq!(typecheck_err!("{ { type A = X; 1 }; fn a: A => a }").code, Some(ErrorCode::UnknownType));
+        assert_eq!(typecheck!("{ { type A = X; 1 }; type A = Y | Z; Y == Z }"), TyKind::Bool.to_ty());
+    }
+
+    #[test] fn unbound_type_variable_fails() { assert!(crate::generate_ast("{ type Option<a> = None | Some(b); None }").is_err()) }
+
+    #[test] fn typeof_match_tuple() { assert_eq!(typecheck!("match (1, true) { (x, true) => x, (_, false) => 0 }"), TyKind::I64.to_ty()) }
+
+    #[test]
+    fn typeof_match_constructors() {
+        let t = typecheck!("{ type Option<a> = None | Some(a); fn o => match o { Some(x) => x, None => 0 } }");
+        assert_eq!(t, arrow!(adt("Option", vec![TyKind::I64.to_ty()]).singleton() => TyKind::I64.to_ty()))
+    }
+
+    #[test]
+    fn non_exhaustive_constructors() {
+        let err = typecheck_err!("{ type Option<a> = None | Some(a); fn o => match o { Some(x) => x } }");
+        assert_eq!(err.msg, "Non-exhaustive patterns: `None` not covered")
+    }
+
+    #[test]
+    fn non_exhaustive_nested_constructors() {
+        let err = typecheck_err!("{ type List<a> = Nil | Cons(a, List<a>); fn l => match l { Nil => 0, Cons(x, Nil) => x } }");
+        assert_eq!(err.msg, "Non-exhaustive patterns: `Cons(_, Cons(_, _))` not covered")
+    }
+
+    #[test]
+    fn non_exhaustive_bool() {
+        assert_eq!(typecheck_err!("match (1, true) { (_, true) => 1 }").msg, "Non-exhaustive patterns: `(_, false)` not covered")
+    }
+
+    #[test]
+    fn non_exhaustive_int() {
+        assert_eq!(typecheck_err!("match 1 { 0 => 1, 1 => 2 }").msg, "Non-exhaustive patterns: `_` not covered")
+    }
+
+    #[test]
+    fn unreachable_arm() {
+        let err = typecheck_err!("match 1 { _ => 1, 2 => 2 }");
+        assert_eq!(err.msg, "Unreachable pattern `2`");
+        assert_eq!(err.span.lo, 18)
+    }
+
+    #[test]
+    fn reports_unreachable_and_missing_arms() {
+        let errors = crate::generate_ast("match (1, true) { (_, true) => 1, (0, false) => 2, (0, _) => 3 }").unwrap_err();
+        let unreachable = errors.iter().find(|err| err.code == Some(ErrorCode::UnreachablePattern)).unwrap();
+        // Both earlier arms are needed to cover `(0, _)`
+        assert_eq!(unreachable.labels.iter().map(|label| label.span.lo).collect::<Vec<_>>(), vec![18, 34]);
+        assert!(errors.iter().any(|err| err.code == Some(ErrorCode::NonExhaustive)))
+    }
+
+    #[test]
+    fn mismatch_is_labelled_with_annotation() {
+        let err = typecheck_err!("{ let f = fn x: Int => x; f(true) }");
+        assert_eq!(err.code, Some(ErrorCode::TypeMismatch));
+        assert_eq!(err.labels[0].msg, "expected `i64` because of this");
+        assert_eq!(err.labels[0].span.lo, 16)
+    }
+
+    #[test]
+    fn non_exhaustive_has_help() {
+        let err = typecheck_err!("{ type Option<a> = None | Some(a); fn o => match o { Some(x) => x } }");
+        assert_eq!(err.help.unwrap(), "add an arm for `None` or a wildcard `_` arm")
+    }
+
+    #[test] fn pattern_type_mismatch_fails() { assert!(crate::generate_ast("match 1 { true => 1, _ => 2 }").is_err()) }
+    #[test] fn duplicate_pattern_binding_fails() { assert!(crate::generate_ast("match (1, 2) { (x, x) => x }").is_err()) }
+    #[test] fn pattern_binding_is_scoped_to_arm() { assert!(crate::generate_ast("{ match 1 { x => x }; x }").is_err()) }
+
+    #[test]
+    fn reports_every_error() {
+        let src = "{ 1 + true; y; if 1 then 2 else 3; \"a\" ++ 1; match 1 { 1 => 1 } }";
+        let errors = crate::generate_ast(src).unwrap_err();
+        assert_eq!(errors.len(), 5);
+        assert!(errors.windows(2).all(|w| w[0].span.lo <= w[1].span.lo))
+    }
+
+    #[test]
+    fn failed_binding_does_not_cascade() {
+        let errors = crate::generate_ast("{ let x = y; x + 1; x ++ \"a\" }").unwrap_err();
+        assert_eq!(errors.len(), 1)
+    }
+
     #[test]
     fn typeof_tuple() {
         assert_eq!(
@@ -204,7 +1177,137 @@ mod test {
         assert_eq!(t, expected)
     }
 
+    #[test]
+    fn argument_count_mismatch() {
+        let err = typecheck_err!("{ let f = fn (x, y) => x; f(1) }");
+        assert_eq!(err.code, Some(ErrorCode::ArgumentCount));
+        assert_eq!(err.msg, "Expected 2 arguments, found 1");
+        assert_eq!(err.span.lo, 26);
+        // A lambda passed where a function of a different arity is expected
+        let err = typecheck_err!("(fn f => f(1, 2))(fn x => x)");
+        assert_eq!((err.code, err.span.lo), (Some(ErrorCode::ArgumentCount), 18));
+    }
+
+    #[test]
+    fn tuple_arity_mismatch() {
+        let err = typecheck_err!("{ let t: (Int, Bool) = (1,); t }");
+        assert_eq!(err.code, Some(ErrorCode::TupleArity));
+        assert_eq!(err.msg, "Expected a tuple of 2 elements, found 1");
+        assert_eq!(typecheck_err!("(1, 2) == (1, 2, 3)").code, Some(ErrorCode::TupleArity));
+    }
+
+    #[test]
+    fn typeof_long_block() {
+        let src = format!("{{ {} 1 }}", (0..2000).map(|i| format!("({}, true) == (1, false);", i)).collect::<String>());
+        assert_eq!(typecheck!(&src), TyKind::I64.to_ty())
+    }
+
+    #[test]
+    fn typeof_many_lets() {
+        let src = format!("{{ let x0 = 0; {} x1999 }}", (1..2000).map(|i| format!("let x{} = x{} + 1;", i, i - 1)).collect::<String>());
+        assert_eq!(typecheck!(&src), TyKind::I64.to_ty());
+        // Every binding in the chain is generalized
+        let src = format!("let x0 = fn x => x in {} (x29(1), x29(true))", (1..30).map(|i| format!(" let x{} = x{} in", i, i - 1)).collect::<String>());
+        assert_eq!(typecheck!(&src), TyKind::Tuple(vec![TyKind::I64.to_ty(), TyKind::Bool.to_ty()]).to_ty());
+    }
+
+    #[test]
+    fn traces_let_group_constraints() {
+        let mut traced = vec![];
+        let mut tracer = |_: Stage, msg: &str| traced.push(msg.to_owned());
+        let tokens = crate::Lexer::new("{ let f = fn x => g(x); let g = fn y => f(y) + 1; f(2) }").lex().unwrap();
+        let mut gen = Counter::new();
+        let mut expr = crate::Parser::new(&tokens, &mut gen).parse().unwrap();
+        Typechecker::new(&mut gen).with_tracer(&mut tracer).typecheck(&mut expr).unwrap();
+        assert!(traced[0].starts_with("constraints of `f`, `g`: "));
+        assert!(traced[1].starts_with("constraints: "));
+    }
+
+    /// Typechecks `depth` nested let-in expressions on a thread with enough stack for the recursion
+    fn typecheck_nested_lets(depth: usize, generalization: Generalization) -> Ty {
+        let src = format!("let x0 = fn x => x in {} (x{}(1), x{}(true))", (1..depth).map(|i| format!(" let x{} = fn y => x{}(y) in", i, i - 1)).collect::<String>(), depth - 1, depth - 1);
+        std::thread::Builder::new().stack_size(64 << 20).spawn(move || {
+            let tokens = crate::Lexer::new(&src).lex().unwrap();
+            let mut gen = Counter::new();
+            let mut expr = crate::Parser::new(&tokens, &mut gen).parse().unwrap();
+            Typechecker::new(&mut gen).with_generalization(generalization).typecheck(&mut expr).unwrap()
+        }).unwrap().join().unwrap()
+    }
+
+    #[bench] fn bench_nested_lets_by_level(b: &mut Bencher) { b.iter(|| typecheck_nested_lets(200, Generalization::Levels)) }
+    #[bench] fn bench_nested_lets_by_env_scan(b: &mut Bencher) { b.iter(|| typecheck_nested_lets(200, Generalization::EnvScan)) }
+
+    #[test]
+    fn typeof_record() {
+        let record = |fields: Vec<(&str, TyKind)>, row: Option<TyKind>| {
+            TyKind::record(fields.into_iter().map(|(name, kind)| (name.to_owned(), kind.to_ty())).collect(), row.map(TyKind::to_ty)).to_ty()
+        };
+        let var = |name: &str| TyKind::TyVar(name.to_owned());
+        assert_eq!(typecheck!("{ y = true, x = 1 }"), record(vec![("x", TyKind::I64), ("y", TyKind::Bool)], None));
+        assert_eq!(typecheck!("{ x = 1, y = true }.y"), TyKind::Bool.to_ty());
+        assert_eq!(typecheck!("fn r => r.x"), arrow!(record(vec![("x", var("a"))], Some(var("b"))).singleton() => var("a").to_ty()));
+        assert_eq!(typecheck!("fn r => { r | x = r.x + 1 }"), arrow!(record(vec![("x", TyKind::I64)], Some(var("a"))).singleton() => record(vec![("x", TyKind::I64)], Some(var("a")))));
+        // Field access works on any record with the field
+        let src = "let get = fn r => r.x in (get({ x = 1 }), get({ y = 2, x = true }))";
+        assert_eq!(typecheck!(src), TyKind::Tuple(vec![TyKind::I64.to_ty(), TyKind::Bool.to_ty()]).to_ty());
+        assert_eq!(typecheck!("{ let r: { x: Int, y: Bool } = { y = true, x = 1 }; { r | y = false } }"), record(vec![("x", TyKind::I64), ("y", TyKind::Bool)], None));
+    }
+
+    #[test]
+    fn record_field_errors() {
+        let err = typecheck_err!("{ x = 1 }.y");
+        assert_eq!((err.code, err.msg.as_str(), err.span.lo), (Some(ErrorCode::MissingField), "Record { x: i64 } has no field `y`", 0));
+        assert_eq!(err.labels[0].span.lo, 10);
+        assert_eq!(typecheck_err!("let r = { x = 1 } in { r | y = 2 }").code, Some(ErrorCode::MissingField));
+        let err = typecheck_err!("{ let f = fn (r: { x: Int }) => r.x; f({ x = 1, y = 2 }) }");
+        assert_eq!((err.code, err.msg.as_str()), (Some(ErrorCode::UnexpectedField), "Record { x: i64, y: i64 } has unexpected field `y`"));
+        assert_eq!(typecheck_err!("{ x = 1, y = 2, x = 3 }").code, Some(ErrorCode::DuplicateField));
+        assert_eq!(typecheck_err!("let r = { x = 1 } in { r | x = true }").code, Some(ErrorCode::TypeMismatch));
+    }
+
+    #[test]
+    fn typeof_let_in() {
+        assert_eq!(typecheck!("{ let x = 5 in x; 5 }"), TyKind::I64.to_ty());
+        assert_eq!(typecheck!("let id = fn x => x in (id(1), id(true))"), TyKind::Tuple(vec![TyKind::I64.to_ty(), TyKind::Bool.to_ty()]).to_ty());
+        assert_eq!(typecheck!("let x = true in let x = if x then 1 else 2 in x"), TyKind::I64.to_ty());
+        // The binding is only in scope in the body
+        assert_eq!(typecheck_err!("{ let x = 1 in x; x }").code, Some(ErrorCode::UnboundVariable));
+    }
+
+    #[test]
+    fn lambda_bound_variables_are_monomorphic() {
+        assert!(crate::generate_ast("fn f => (f(1), f(true))").is_err());
+        // Binding a lambda bound variable again does not make it polymorphic
+        assert!(crate::generate_ast("fn x => { let y = x; (y + 1, y == true) }").is_err());
+        assert!(crate::generate_ast("fn x => { let y = x; (y(1), y(true)) }").is_err());
+    }
+
+    #[test]
+    fn nested_lets_keep_outer_variables_free() {
+        let bool_int = TyKind::Tuple(vec![TyKind::Bool.to_ty(), TyKind::I64.to_ty()]).to_ty();
+        let int_bool = TyKind::Tuple(vec![TyKind::I64.to_ty(), TyKind::Bool.to_ty()]).to_ty();
+        assert_eq!(
+            typecheck!("{ let f = fn x => { let g = fn y => (x, y); g }; (f(1)(true), f(false)(2)) }"),
+            TyKind::Tuple(vec![int_bool, bool_int]).to_ty()
+        );
+        assert!(crate::generate_ast("{ let f = fn x => { let g = fn y => if true then x else y; g }; f(1)(true) }").is_err());
+    }
+
+    #[test]
+    fn application_results_are_generalized() {
+        // There is no mutation so generalizing a non-value is sound and no value restriction is needed
+        assert_eq!(typecheck!("{ let id = fn x => x; let g = id(id); (g(1), g(true)) }"), TyKind::Tuple(vec![TyKind::I64.to_ty(), TyKind::Bool.to_ty()]).to_ty());
+    }
 
+    #[test]
+    fn scopes_are_balanced() {
+        assert_eq!(typecheck!("{ let x = true; { let x = 1; x }; x }"), TyKind::Bool.to_ty());
+        assert_eq!(typecheck!("{ let x = true; match 1 { x => x }; (fn x => x + 1)(2); x }"), TyKind::Bool.to_ty());
+        assert_eq!(typecheck_err!("{ { let x = 1; x }; { { let y = 2; y }; y } }").code, Some(ErrorCode::UnboundVariable));
+        // A failing arm still pops its scope
+        let errs = crate::generate_ast("{ match (1, 2) { (a, a) => 1 }; a }").unwrap_err();
+        assert_eq!(errs.iter().map(|err| err.code).collect::<Vec<_>>(), vec![Some(ErrorCode::DuplicateBinding), Some(ErrorCode::UnboundVariable)]);
+    }
 }
 
 
@@ -1,6 +1,6 @@
 use std::fmt::{self, Display, Formatter, Debug};
-use super::{Type, Substitution, TyScheme, Env};
-use std::collections::HashSet;
+use super::{Type, Substitution, TyScheme, Predicate};
+use std::collections::{HashSet, HashMap};
 use crate::set;
 use crate::parsing::Span;
 
@@ -19,23 +19,36 @@ impl Ty {
         Self { span, kind }
     }
 
-    pub(crate) fn generalize(self, env: &Env<&str, TyScheme>) -> TyScheme {
-        let forall = &self.ftv() - &env.ftv();
-        TyScheme::new(self, forall)
-    }
-
-    pub(crate) fn erased() -> Self {
-        Self { span: Span::single(0, 0), kind: TyKind::Erased }
+    /// Quantifies over the generalizable variables along with the predicates on them
+    pub(crate) fn generalize(self, generalizable: impl Fn(u64) -> bool, preds: &[Predicate]) -> TyScheme {
+        let forall = self.ftv().into_iter().filter(|&i| generalizable(i)).collect();
+        TyScheme::qualified(self, forall, preds)
     }
 
     /// wraps Ty into singleton tuple
+    #[cfg(test)]
     pub(crate) fn singleton(self) -> Self {
         let span = self.span;
         Self::new(span, TyKind::Tuple(vec![self]))
     }
 
-    pub(crate) fn take(&mut self) -> Self {
-        std::mem::replace(self, Ty::erased())
+    /// Copy of the type that reports errors at the given span instead
+    pub(crate) fn with_span(&self, span: Span) -> Self {
+        Self::new(span, self.kind.clone())
+    }
+
+    /// Replaces the named type variables with the corresponding inference variables
+    pub(crate) fn instantiate_params(&mut self, vars: &HashMap<&str, u64>) {
+        match &mut self.kind {
+            TyKind::TyVar(name) => if let Some(i) = vars.get(name.as_str()) { self.kind = TyKind::Infer(*i) },
+            TyKind::Tuple(xs) | TyKind::Adt(_, xs) => xs.iter_mut().for_each(|t| t.instantiate_params(vars)),
+            TyKind::Record(fields, row) => {
+                fields.iter_mut().for_each(|(_, t)| t.instantiate_params(vars));
+                if let Some(row) = row { row.instantiate_params(vars) }
+            }
+            TyKind::Arrow(box l, box r) => { l.instantiate_params(vars); r.instantiate_params(vars); }
+            TyKind::Bool | TyKind::Str | TyKind::F64 | TyKind::I64 | TyKind::Erased | TyKind::Infer(_) => {}
+        }
     }
 
 }
@@ -53,16 +66,33 @@ impl Display for Ty {
 pub enum TyKind {
     Erased,
     Bool,
+    Str,
     I64,
     F64,
     Infer(u64), // Unification type variable
     TyVar(String),
     Tuple(Vec<Ty>),
+    /// Fields sorted by name along with the row variable standing for the other fields of an open record
+    Record(Vec<(String, Ty)>, Option<Box<Ty>>),
     Arrow(Box<Ty>, Box<Ty>),
+    Adt(String, Vec<Ty>), // Algebraic data type applied to its type arguments
 }
 
 impl TyKind {
     pub fn unit() -> Self { Self::Tuple(Vec::new()) }
+
+    /// Sorts the fields and absorbs the fields of a row that has been solved to another record
+    pub fn record(mut fields: Vec<(String, Ty)>, row: Option<Ty>) -> Self {
+        let row = match row {
+            Some(Ty { kind: Self::Record(more, row), .. }) => {
+                fields.extend(more);
+                row
+            }
+            row => row.map(Box::new),
+        };
+        fields.sort_by(|(x, _), (y, _)| x.cmp(y));
+        Self::Record(fields, row)
+    }
 }
 
 impl Type for TyKind {
@@ -70,9 +100,16 @@ impl Type for TyKind {
         match self {
             Self::Infer(i) => if let Some(t) = s.get(i) { *self = t.kind.clone() }
             Self::Tuple(xs) => xs.iter_mut().for_each(|t| t.apply(s)),
+            Self::Record(fields, row) => {
+                fields.iter_mut().for_each(|(_, t)| t.apply(s));
+                if let Some(row) = row { row.apply(s) }
+                let (fields, row) = (std::mem::take(fields), row.take().map(|row| *row));
+                *self = Self::record(fields, row);
+            }
             Self::Arrow(box l, box r) => { l.apply(s); r.apply(s); }
-            Self::Bool | Self::F64 | Self::I64 | Self::Erased => {},
-            Self::TyVar(_n) => unimplemented!(),
+            Self::Adt(_, xs) => xs.iter_mut().for_each(|t| t.apply(s)),
+            Self::Bool | Self::Str | Self::F64 | Self::I64 | Self::Erased => {},
+            Self::TyVar(_n) => {},
         }
     }
 
@@ -84,9 +121,16 @@ impl Type for TyKind {
             Self::Tuple(xs) => xs.iter()
                 .map(|x| x.ftv())
                 .fold(HashSet::new(), |acc, x| &acc | &x),
+            Self::Record(fields, row) => fields.iter()
+                .map(|(_, t)| t.ftv())
+                .fold(row.as_ref().map_or_else(HashSet::new, |row| row.ftv()), |acc, x| &acc | &x),
             Self::Arrow(l, r) => &l.ftv() | &r.ftv(),
-            Self::Bool | Self::F64 | Self::I64 | Self::Erased => HashSet::new(),
-            Self::TyVar(_n) => unimplemented!(),
+            Self::Adt(_, xs) => xs.iter()
+                .map(|x| x.ftv())
+                .fold(HashSet::new(), |acc, x| &acc | &x),
+            Self::Bool | Self::Str | Self::F64 | Self::I64 | Self::Erased => HashSet::new(),
+            // Named type variables are rigid
+            Self::TyVar(_n) => HashSet::new(),
         }
     }
 }
@@ -98,9 +142,20 @@ impl Display for TyKind {
             Self::I64          => write!(f, "i64"),
             Self::F64          => write!(f, "f64"),
             Self::Bool         => write!(f, "bool"),
+            Self::Str          => write!(f, "str"),
             Self::Tuple(xs)    => write!(f, "({})", xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
+            Self::Record(fields, row) => {
+                let fields = fields.iter().map(|(name, t)| format!("{}: {}", name, t)).collect::<Vec<_>>().join(", ");
+                match row {
+                    Some(row) => write!(f, "{{ {} | {} }}", fields, row),
+                    None if fields.is_empty() => write!(f, "{{}}"),
+                    None      => write!(f, "{{ {} }}", fields),
+                }
+            }
             Self::TyVar(name)     => write!(f, "{}", name),
             Self::Erased       => write!(f, "τ"),
+            Self::Adt(name, xs) if xs.is_empty() => write!(f, "{}", name),
+            Self::Adt(name, xs) => write!(f, "{}<{}>", name, xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
             Self::Arrow(box l, r)  => match l.kind {
                 Self::Arrow(..) => write!(f, "({}) -> {}", l, r),
                 _               => write!(f, "{} -> {}", l, r),
@@ -112,7 +167,7 @@ impl Display for TyKind {
 
 /// Convenience method for testing
 #[cfg(test)]
-impl TyKind { pub fn to_ty(self) -> Ty { Ty::new(Span::single(0, 0), self) } }
+impl TyKind { pub fn to_ty(self) -> Ty { Ty::new(Span::single(0), self) } }
 
 #[cfg(test)]
 mod test {
@@ -122,7 +177,7 @@ mod test {
     fn test_ftv() {
         let a = TyKind::Infer(0).to_ty();
         let b = TyKind::Tuple(vec![TyKind::F64.to_ty(), TyKind::Infer(2).to_ty()]).to_ty();
-        let f = TyKind::Arrow(box a, box b);
+        let f = TyKind::Arrow(Box::new(a), Box::new(b));
         assert_eq!(f.ftv(), set! { 0, 2 })
     }
 }
@@ -1,4 +1,4 @@
-use super::{Ty, TyKind, Substitution, Type};
+use super::{Ty, TyKind, Substitution, Type, Predicate};
 use std::collections::HashSet;
 use crate::util::Counter;
 use std::fmt::{self, Formatter, Display};
@@ -7,42 +7,68 @@ use std::fmt::{self, Formatter, Display};
 pub(crate) struct TyScheme {
     ty: Ty,
     forall: HashSet<u64>,
+    /// Predicates on the quantified variables which every instantiation must satisfy
+    preds: Vec<Predicate>,
 }
 
 impl TyScheme {
     pub fn new(ty: Ty, forall: HashSet<u64>) -> Self {
-        Self { ty, forall }
+        Self { ty, forall, preds: vec![] }
     }
 
-    pub fn instantiate(&self, name_gen: &mut Counter) -> Ty {
+    /// Keeps the predicates that only constrain quantified variables
+    pub fn qualified(ty: Ty, forall: HashSet<u64>, preds: &[Predicate]) -> Self {
+        let preds = preds.iter().filter(|pred| pred.ftv().is_subset(&forall)).cloned().collect();
+        Self { ty, forall, preds }
+    }
+
+    pub fn ty(&self) -> &Ty {
+        &self.ty
+    }
+
+    pub fn preds(&self) -> &[Predicate] {
+        &self.preds
+    }
+
+    /// The instantiated type along with the predicates it must satisfy
+    pub fn instantiate(&self, name_gen: &mut Counter) -> (Ty, Vec<Predicate>) {
         let substitution: Substitution = self.forall.iter()
             .map(|t| (*t, Ty::new(self.ty.span, TyKind::Infer(name_gen.next())))).collect();
         let mut ty = self.ty.clone();
         ty.apply(&substitution);
-        ty
+        let preds = self.preds.iter().cloned().map(|mut pred| { pred.apply(&substitution); pred }).collect();
+        (ty, preds)
     }
 }
 
 impl Display for TyScheme {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "∀{}.{}", self.forall.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","), self.ty)
+        write!(f, "∀{}.", self.forall.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","))?;
+        if !self.preds.is_empty() { write!(f, "{} => ", self.preds.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "))? }
+        write!(f, "{}", self.ty)
     }
 }
 
 impl Type for TyScheme {
-    fn ftv(&self) -> HashSet<u64> { self.ty.ftv() }
-    fn apply(&mut self, s: &Substitution) { self.ty.apply(s) }
+    fn ftv(&self) -> HashSet<u64> { &self.ty.ftv() - &self.forall }
+
+    /// Quantified variables are bound by the scheme so the substitution leaves them alone
+    fn apply(&mut self, s: &Substitution) {
+        let s: Substitution = s.iter().filter(|(k, _)| !self.forall.contains(k)).map(|(k, t)| (*k, t.clone())).collect();
+        self.ty.apply(&s);
+        self.preds.iter_mut().for_each(|pred| pred.apply(&s))
+    }
 }
 
 /// Creates new typescheme with no bound variables
 impl From<Ty> for TyScheme {
     fn from(ty: Ty) -> Self {
-        Self { ty, forall: HashSet::new() }
+        Self { ty, forall: HashSet::new(), preds: vec![] }
     }
 }
 
 impl From<&Ty> for TyScheme {
     fn from(ty: &Ty) -> Self {
-        Self { ty: ty.clone(), forall: HashSet::new() }
+        Self { ty: ty.clone(), forall: HashSet::new(), preds: vec![] }
     }
 }
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use super::{Ty, TyKind, Type, Constraint, Substitution};
+use crate::error::{Error, ErrorCode};
+use crate::parsing::Span;
+use crate::util::Counter;
+
+/// Union-find store of inference variables
+/// Unified variables share a representative, which is bound in place once it is unified with anything else
+/// Representatives have the level of the innermost let whose bound they were created in, or of the outermost let they escape to
+/// Variables are only registered with a level once they are seen so they default to the level they are first solved at
+/// Variables that were never seen are treated as belonging to the current level so they are never generalized
+#[derive(Default)]
+pub(crate) struct Unifier {
+    /// Variables that are not representatives point towards theirs
+    parents: HashMap<u64, u64>,
+    ranks: HashMap<u64, u32>,
+    bindings: HashMap<u64, Ty>,
+    levels: HashMap<u64, usize>,
+    level: usize,
+}
+
+impl Unifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entered for the bound of each let
+    pub fn enter_level(&mut self) { self.level += 1 }
+
+    pub fn exit_level(&mut self) { self.level -= 1 }
+
+    /// Places the variables of the type at the current level unless they are already known
+    /// Variables entering the environment must be registered where they are bound
+    pub fn register(&mut self, t: &Ty) {
+        for i in t.ftv() {
+            let root = self.find(i);
+            let level = self.level;
+            self.levels.entry(root).or_insert(level);
+        }
+    }
+
+    /// Whether the variable belongs to a let inside the current level and so may be generalized
+    pub fn is_generalizable(&self, i: u64) -> bool {
+        self.level_of(self.root(i)) > self.level
+    }
+
+    /// Solving continues past failed unifications and the constraint is worked through without recursion
+    /// `name_gen` supplies the rows shared by two open records that each have fields the other lacks
+    pub fn solve(&mut self, constraint: Constraint, name_gen: &mut Counter) -> Vec<Error> {
+        let mut errors = vec![];
+        let mut stack = vec![constraint];
+        while let Some(c) = stack.pop() {
+            match c {
+                Constraint::And(c, d) => {
+                    stack.push(*d);
+                    stack.push(*c);
+                }
+                Constraint::Eq(t, u) => self.unify(t, u, name_gen, &mut errors),
+                // Predicates are resolved against the instances by the typechecker
+                Constraint::Empty | Constraint::Pred(_) => {}
+            }
+        }
+        errors
+    }
+
+    /// Maps every solved variable to its type with all variables inside it resolved as well
+    pub fn substitution(&self) -> Substitution {
+        let mut cache = HashMap::new();
+        let vars = self.parents.keys().chain(self.bindings.keys()).copied().collect::<Vec<_>>();
+        vars.into_iter().map(|i| (i, self.zonk(&Ty::new(Span::single(0), TyKind::Infer(i)), &mut cache)))
+            .filter(|(i, t)| t.kind != TyKind::Infer(*i))
+            .collect()
+    }
+
+    fn unify(&mut self, t: Ty, u: Ty, name_gen: &mut Counter, errors: &mut Vec<Error>) {
+        let mut pairs = vec![(t, u)];
+        while let Some((t, u)) = pairs.pop() {
+            let (t, u) = (self.resolve(t), self.resolve(u));
+            match (t.kind, u.kind) {
+                (TyKind::Infer(i), TyKind::Infer(j)) => if i != j { self.union(i, j) },
+                (TyKind::Infer(i), y) => if let Err(err) = self.bind(i, Ty::new(t.span, y)) { errors.push(err) },
+                (x, TyKind::Infer(j)) => if let Err(err) = self.bind(j, Ty::new(u.span, x)) { errors.push(err) },
+                (TyKind::Arrow(box l, box r), TyKind::Arrow(box t, box u)) => match (&l.kind, &t.kind) {
+                    // Parameters are a tuple so differing lengths mean the function is given the wrong number of arguments
+                    (TyKind::Tuple(xs), TyKind::Tuple(ys)) if xs.len() != ys.len() => {
+                        let error = Error::new(l.span, format!("Expected {} arguments, found {}", ys.len(), xs.len())).with_code(ErrorCode::ArgumentCount);
+                        errors.push(if l.span == t.span { error } else { error.with_label(t.span, format!("expected {} arguments because of this", ys.len())) });
+                        pairs.push((r, u));
+                    }
+                    _ => pairs.extend(vec![(r, u), (l, t)]),
+                }
+                // The unit type is reported as an ordinary mismatch
+                (TyKind::Tuple(xs), TyKind::Tuple(ys)) if xs.len() != ys.len() && !xs.is_empty() && !ys.is_empty() => {
+                    let error = Error::new(t.span, format!("Expected a tuple of {} elements, found {}", ys.len(), xs.len())).with_code(ErrorCode::TupleArity);
+                    errors.push(if t.span == u.span { error } else { error.with_label(u.span, format!("expected {} elements because of this", ys.len())) });
+                }
+                (TyKind::Tuple(xs), TyKind::Tuple(ys)) if xs.len() == ys.len() => pairs.extend(xs.into_iter().zip(ys).rev()),
+                (TyKind::Adt(n, xs), TyKind::Adt(m, ys)) if n == m && xs.len() == ys.len() => pairs.extend(xs.into_iter().zip(ys).rev()),
+                (x @ TyKind::Record(..), y @ TyKind::Record(..)) => self.unify_records(&Ty::new(t.span, x), &Ty::new(u.span, y), &mut pairs, name_gen, errors),
+                (x, y) if x == y => {}
+                (x, y) => errors.push(self.mismatch(&Ty::new(t.span, x), &Ty::new(u.span, y))),
+            }
+        }
+    }
+
+    /// Fields present in both records are unified and each open row is solved to the fields only the other record has
+    fn unify_records(&mut self, t: &Ty, u: &Ty, pairs: &mut Vec<(Ty, Ty)>, name_gen: &mut Counter, errors: &mut Vec<Error>) {
+        let (xs, r) = self.row(t);
+        let (mut only_y, q) = self.row(u);
+        let mut only_x = vec![];
+        for (name, x) in xs {
+            match only_y.iter().position(|(other, _)| *other == name) {
+                Some(i) => pairs.push((x, only_y.remove(i).1)),
+                None => only_x.push((name, x)),
+            }
+        }
+        if r.is_none() {
+            for (name, _) in &only_y { errors.push(self.field_error(t, u, format!("has no field `{}`", name), ErrorCode::MissingField)) }
+        }
+        if q.is_none() {
+            for (name, _) in &only_x { errors.push(self.field_error(t, u, format!("has unexpected field `{}`", name), ErrorCode::UnexpectedField)) }
+        }
+        let record = |span, fields, row| Ty::new(span, TyKind::record(fields, row));
+        match (r, q) {
+            (None, None) => {}
+            (Some(r), None) => pairs.push((r, record(u.span, only_y, None))),
+            (None, Some(q)) => pairs.push((q, record(t.span, only_x, None))),
+            // A row cannot be extended with fields of its own
+            (Some(r), Some(q)) if r == q => if !only_x.is_empty() || !only_y.is_empty() { errors.push(self.mismatch(t, u)) },
+            (Some(r), Some(q)) => match (only_x.is_empty(), only_y.is_empty()) {
+                (true, true) => pairs.push((r, q)),
+                (true, false) => pairs.push((r, record(u.span, only_y, Some(q)))),
+                (false, true) => pairs.push((q, record(t.span, only_x, Some(r)))),
+                (false, false) => {
+                    let rest = Ty::new(t.span, TyKind::Infer(name_gen.next()));
+                    pairs.push((r, record(u.span, only_y, Some(rest.clone()))));
+                    pairs.push((q, record(t.span, only_x, Some(rest))));
+                }
+            }
+        }
+    }
+
+    /// The fields of a record including those its row has been solved to, along with the row if it is still open
+    fn row(&mut self, record: &Ty) -> (Vec<(String, Ty)>, Option<Ty>) {
+        let mut fields = vec![];
+        let mut row = Some(record.clone());
+        while let Some(t) = row.take() {
+            let t = self.resolve(t);
+            match t.kind {
+                TyKind::Record(more, rest) => {
+                    fields.extend(more);
+                    row = rest.map(|rest| *rest);
+                }
+                _ => {
+                    row = Some(t);
+                    break
+                }
+            }
+        }
+        fields.sort_by(|(x, _), (y, _)| x.cmp(y));
+        (fields, row)
+    }
+
+    fn mismatch(&self, t: &Ty, u: &Ty) -> Error {
+        let mut cache = HashMap::new();
+        let (x, y) = (self.zonk(t, &mut cache), self.zonk(u, &mut cache));
+        mismatch(t.span, x.kind, u.span, y.kind)
+    }
+
+    /// Reported at the first record with the second as the expected one
+    fn field_error(&self, t: &Ty, u: &Ty, problem: String, code: ErrorCode) -> Error {
+        let mut cache = HashMap::new();
+        let (x, y) = (self.zonk(t, &mut cache), self.zonk(u, &mut cache));
+        let error = Error::new(t.span, format!("Record {} {}", x, problem)).with_code(code);
+        if t.span == u.span { error } else { error.with_label(u.span, format!("expected `{}` because of this", y)) }
+    }
+
+    /// The representative of the variable, pointing every variable on the way directly at it
+    fn find(&mut self, i: u64) -> u64 {
+        let root = self.root(i);
+        let mut node = i;
+        while node != root {
+            let parent = self.parents[&node];
+            self.parents.insert(node, root);
+            node = parent;
+        }
+        root
+    }
+
+    /// Finds the representative without compressing the path so it can be used while the store is borrowed
+    fn root(&self, mut i: u64) -> u64 {
+        while let Some(&parent) = self.parents.get(&i) { i = parent }
+        i
+    }
+
+    /// Both variables must be unbound representatives and the new representative takes the lower level
+    fn union(&mut self, i: u64, j: u64) {
+        let level = self.level_of(i).min(self.level_of(j));
+        let (ri, rj) = (*self.ranks.get(&i).unwrap_or(&0), *self.ranks.get(&j).unwrap_or(&0));
+        let root = if ri > rj {
+            self.parents.insert(j, i);
+            i
+        } else {
+            self.parents.insert(i, j);
+            if ri == rj { self.ranks.insert(j, rj + 1); }
+            j
+        };
+        self.levels.insert(root, level);
+    }
+
+    fn level_of(&self, root: u64) -> usize {
+        self.levels.get(&root).copied().unwrap_or(self.level)
+    }
+
+    /// The variables of a type bound to a variable escape to that variable's level
+    fn lower(&mut self, t: &Ty, level: usize) {
+        let mut stack = vec![t.clone()];
+        while let Some(t) = stack.pop() {
+            match t.kind {
+                TyKind::Infer(j) => {
+                    let root = self.find(j);
+                    match self.bindings.get(&root) {
+                        Some(bound) => stack.push(bound.clone()),
+                        None => {
+                            let lowered = self.level_of(root).min(level);
+                            self.levels.insert(root, lowered);
+                        }
+                    }
+                }
+                TyKind::Tuple(xs) | TyKind::Adt(_, xs) => stack.extend(xs),
+                TyKind::Record(fields, row) => stack.extend(fields.into_iter().map(|(_, t)| t).chain(row.map(|row| *row))),
+                TyKind::Arrow(box l, box r) => stack.extend(vec![l, r]),
+                TyKind::Bool | TyKind::Str | TyKind::F64 | TyKind::I64 | TyKind::Erased | TyKind::TyVar(_) => {}
+            }
+        }
+    }
+
+    /// Replaces a variable with its representative or the type it is bound to
+    /// Only the outermost type is resolved and it keeps the span of the variable
+    fn resolve(&mut self, t: Ty) -> Ty {
+        match t.kind {
+            TyKind::Infer(i) => {
+                let root = self.find(i);
+                match self.bindings.get(&root) {
+                    Some(bound) => Ty::new(t.span, bound.kind.clone()),
+                    None => Ty::new(t.span, TyKind::Infer(root)),
+                }
+            }
+            _ => t,
+        }
+    }
+
+    /// Performs the occurs check and if it passes, binds the unbound representative to the type
+    fn bind(&mut self, i: u64, t: Ty) -> Result<(), Error> {
+        if self.occurs(i, &t) {
+            let t = self.zonk(&t, &mut HashMap::new());
+            return Err(Error::new(t.span, format!("Occurs check failed: {} occurs in {}", i, t)).with_code(ErrorCode::InfiniteType))
+        }
+        self.lower(&t, self.level_of(i));
+        self.bindings.insert(i, t);
+        Ok(())
+    }
+
+    fn occurs(&self, i: u64, t: &Ty) -> bool {
+        let mut stack = vec![t];
+        while let Some(t) = stack.pop() {
+            match &t.kind {
+                TyKind::Infer(j) => {
+                    let root = self.root(*j);
+                    if root == i { return true }
+                    if let Some(bound) = self.bindings.get(&root) { stack.push(bound) }
+                }
+                TyKind::Tuple(xs) | TyKind::Adt(_, xs) => stack.extend(xs),
+                TyKind::Record(fields, row) => stack.extend(fields.iter().map(|(_, t)| t).chain(row.as_deref())),
+                TyKind::Arrow(l, r) => stack.extend(vec![&**l, &**r]),
+                TyKind::Bool | TyKind::Str | TyKind::F64 | TyKind::I64 | TyKind::Erased | TyKind::TyVar(_) => {}
+            }
+        }
+        false
+    }
+
+    /// Resolves every variable in the type
+    pub fn apply(&self, t: &Ty) -> Ty {
+        self.zonk(t, &mut HashMap::new())
+    }
+
+    /// `cache` holds the resolved kinds of the representatives seen so far
+    fn zonk(&self, t: &Ty, cache: &mut HashMap<u64, TyKind>) -> Ty {
+        let kind = match &t.kind {
+            TyKind::Infer(i) => {
+                let root = self.root(*i);
+                if let Some(kind) = cache.get(&root) { return Ty::new(t.span, kind.clone()) }
+                let kind = match self.bindings.get(&root) {
+                    Some(bound) => self.zonk(bound, cache).kind,
+                    None => TyKind::Infer(root),
+                };
+                cache.insert(root, kind.clone());
+                kind
+            }
+            TyKind::Tuple(xs) => TyKind::Tuple(xs.iter().map(|x| self.zonk(x, cache)).collect()),
+            TyKind::Record(fields, row) => {
+                let fields = fields.iter().map(|(name, t)| (name.clone(), self.zonk(t, cache))).collect();
+                TyKind::record(fields, row.as_ref().map(|row| self.zonk(row, cache)))
+            }
+            TyKind::Adt(name, xs) => TyKind::Adt(name.clone(), xs.iter().map(|x| self.zonk(x, cache)).collect()),
+            TyKind::Arrow(l, r) => TyKind::Arrow(Box::new(self.zonk(l, cache)), Box::new(self.zonk(r, cache))),
+            kind => kind.clone(),
+        };
+        Ty::new(t.span, kind)
+    }
+}
+
+/// The second type is treated as the expected one and is labelled separately when it comes from elsewhere
+fn mismatch(span: Span, x: TyKind, expected_span: Span, y: TyKind) -> Error {
+    let error = Error::new(span, format!("Failed to unify type {} with {}", x, y)).with_code(ErrorCode::TypeMismatch);
+    if span == expected_span { error }
+    else { error.with_label(expected_span, format!("expected `{}` because of this", y)) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ty(kind: TyKind) -> Ty { Ty::new(Span::single(0), kind) }
+    fn var(i: u64) -> Ty { ty(TyKind::Infer(i)) }
+
+    #[test]
+    fn resolves_chains_of_variables() {
+        let mut unifier = Unifier::new();
+        let chain = (1..100).map(|i| Constraint::Eq(var(i), var(i + 1))).collect();
+        let arrow = ty(TyKind::Arrow(Box::new(ty(TyKind::Tuple(vec![var(1)]))), Box::new(var(200))));
+        let errors = unifier.solve(Constraint::conj(vec![Constraint::conj(chain), Constraint::Eq(var(0), arrow), Constraint::Eq(var(50), ty(TyKind::I64))]), &mut Counter::new());
+        assert!(errors.is_empty());
+        assert_eq!(unifier.find(1), unifier.find(100));
+        let s = unifier.substitution();
+        assert_eq!(s[&1], ty(TyKind::I64));
+        assert_eq!(s[&100], ty(TyKind::I64));
+        assert_eq!(s[&0], ty(TyKind::Arrow(Box::new(ty(TyKind::Tuple(vec![ty(TyKind::I64)]))), Box::new(var(200)))));
+    }
+
+    #[test]
+    fn occurs_check() {
+        let mut unifier = Unifier::new();
+        let errors = unifier.solve(Constraint::conj(vec![
+            Constraint::Eq(var(1), var(2)),
+            Constraint::Eq(var(2), ty(TyKind::Tuple(vec![var(1), ty(TyKind::Bool)]))),
+        ]), &mut Counter::new());
+        assert_eq!(errors[0].code, Some(ErrorCode::InfiniteType));
+    }
+
+    #[test]
+    fn variables_escaping_to_an_outer_level_are_not_generalizable() {
+        let mut unifier = Unifier::new();
+        unifier.register(&var(0));
+        unifier.enter_level();
+        unifier.register(&var(1));
+        let arrow = ty(TyKind::Arrow(Box::new(ty(TyKind::Tuple(vec![var(0)]))), Box::new(var(2))));
+        let errors = unifier.solve(Constraint::conj(vec![Constraint::Eq(var(1), arrow), Constraint::Eq(var(3), var(0))]), &mut Counter::new());
+        assert!(errors.is_empty());
+        unifier.exit_level();
+        assert!(!unifier.is_generalizable(0));
+        assert!(!unifier.is_generalizable(3));
+        assert!(unifier.is_generalizable(2));
+        // A variable that was never registered or solved may still be in the environment
+        assert!(!unifier.is_generalizable(4));
+    }
+
+    #[test]
+    fn open_records_share_the_rest_of_their_fields() {
+        let field = |name: &str, t: Ty| (name.to_owned(), t);
+        let record = |fields, row| ty(TyKind::record(fields, row));
+        let mut unifier = Unifier::new();
+        let mut name_gen = Counter::new();
+        let t = record(vec![field("x", ty(TyKind::I64)), field("y", var(101))], Some(var(110)));
+        let u = record(vec![field("y", ty(TyKind::Bool)), field("z", var(102))], Some(var(120)));
+        assert!(unifier.solve(Constraint::Eq(t.clone(), u.clone()), &mut name_gen).is_empty());
+        assert_eq!(unifier.apply(&t), unifier.apply(&u));
+        assert_eq!(unifier.apply(&t).to_string(), "{ x: i64, y: bool, z: τ102 | τ1 }");
+        // Closing the row removes the freedom to add fields
+        let closed = record(vec![field("x", var(103)), field("y", var(104)), field("z", ty(TyKind::Str))], None);
+        assert!(unifier.solve(Constraint::Eq(t.clone(), closed), &mut name_gen).is_empty());
+        let errors = unifier.solve(Constraint::Eq(t, record(vec![field("w", var(105))], Some(var(130)))), &mut name_gen);
+        assert_eq!(errors[0].code, Some(ErrorCode::MissingField));
+    }
+
+    #[test]
+    fn solves_deeply_nested_constraints() {
+        let n = 20_000;
+        let c = (0..n).fold(Constraint::Empty, |acc, i| Constraint::And(Box::new(acc), Box::new(Constraint::Eq(var(i), var(i + 1)))));
+        let mut unifier = Unifier::new();
+        assert!(unifier.solve(Constraint::And(Box::new(c), Box::new(Constraint::Eq(var(n), ty(TyKind::Bool)))), &mut Counter::new()).is_empty());
+        assert_eq!(unifier.substitution()[&0], ty(TyKind::Bool));
+    }
+}
@@ -1,4 +1,5 @@
 
+#[allow(dead_code)]
 pub trait Assert<T, E> {
     fn assert(self, p: impl FnOnce(&T) -> bool, err: impl FnOnce() -> E) -> Self;
 }
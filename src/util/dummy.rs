@@ -1,4 +1,5 @@
 /// Trait for types that can produce a dummy instance of themselves
+#[allow(dead_code)]
 pub trait Dummy {
     fn dummy() -> Self;
 }
@@ -0,0 +1,34 @@
+/// Generates the type variable names `a` to `z`, then `aa`, `ab` and so on
+pub struct Generator {
+    i: usize,
+}
+
+impl Generator {
+    pub fn new() -> Self {
+        Self { i: 0 }
+    }
+
+    pub fn gen(&mut self) -> String {
+        let mut n = self.i;
+        self.i += 1;
+        let mut name = vec![];
+        loop {
+            name.push(b'a' + (n % 26) as u8);
+            if n < 26 { break }
+            n = n / 26 - 1;
+        }
+        name.iter().rev().map(|&c| c as char).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generates_longer_names_after_z() {
+        let mut gen = Generator::new();
+        let names = (0..29).map(|_| gen.gen()).collect::<Vec<_>>();
+        assert_eq!((&names[0][..], &names[25][..], &names[26][..], &names[28][..]), ("a", "z", "aa", "ac"));
+    }
+}
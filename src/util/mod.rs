@@ -2,8 +2,11 @@ mod split;
 mod dummy;
 mod assert;
 mod counter;
+mod generator;
 
 pub use dummy::Dummy;
+#[allow(unused_imports)]
 pub use assert::Assert;
 pub use split::split;
 pub use counter::Counter;
+pub use generator::Generator;